@@ -0,0 +1,684 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::CodeError,
+    parser::{
+        Accessor, Expression, ExpressionTypes, FunctionCall, IdentifierExpression,
+        NumberExpression, NumberExpressionTypes, StructDefinition, TextExpression,
+        TextExpressionTypes, ValueExpression, ValueExpressionTypes, VariableType,
+    },
+};
+
+/// Names and types of the fields of a `Struktur` in the declared order
+pub type StructFields = Vec<(String, VariableType)>;
+
+/// Built-in `Struktur` of the error that `Fehler e` catches in a `Versuche` block
+pub const ERROR_STRUCT: &str = "Fehler";
+
+/// Built-in Konstante with the arguments after `--` as Liste of Texts
+pub const ARGS_VARIABLE: &str = "argumente";
+
+/// Declaration of `argumente`, which is not written in the script and so is on line 0.
+pub fn args_declaration() -> IdentifierExpression {
+    IdentifierExpression::new(0, 0, 0, ARGS_VARIABLE.to_owned())
+}
+
+pub fn error_struct_fields() -> StructFields {
+    vec![
+        ("nachricht".to_owned(), VariableType::Text),
+        ("zeile".to_owned(), VariableType::Number),
+    ]
+}
+
+/// Checks a parsed program before it runs, so mistakes are found even in lines that are
+/// never reached. Variables only have a known type after a declaration like `Zahl x` or
+/// `Person p`, everything else is checked by the interpreter.
+pub struct TypeChecker {
+    structs: HashMap<String, StructFields>,
+    variables: HashMap<String, Declaration>,
+    /// Files loaded with `Lade` by their namespace
+    imports: HashMap<String, String>,
+}
+
+/// Where and how a variable was declared, loop variables have no known type.
+struct Declaration {
+    var_expr: IdentifierExpression,
+    var_type: Option<VariableType>,
+    constant: bool,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            structs: HashMap::from([(ERROR_STRUCT.to_owned(), error_struct_fields())]),
+            variables: HashMap::from([(
+                ARGS_VARIABLE.to_owned(),
+                Declaration {
+                    var_expr: args_declaration(),
+                    var_type: Some(VariableType::List),
+                    constant: true,
+                },
+            )]),
+            imports: HashMap::new(),
+        }
+    }
+
+    pub fn check(&mut self, expressions: &[Expression]) -> Result<(), CodeError> {
+        for expression in expressions {
+            self.check_expression(expression, false)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a single instruction, `constant` is set for the declaration after `Konstante`.
+    fn check_expression(
+        &mut self,
+        expression: &Expression,
+        constant: bool,
+    ) -> Result<(), CodeError> {
+        match expression.get_expression() {
+            ExpressionTypes::TextAssignment(var_expr, value) => {
+                self.check_text(value)?;
+                self.declare(var_expr, Some(VariableType::Text), constant)?;
+            }
+            ExpressionTypes::NumberAssignment(var_expr, value) => {
+                self.check_number(value)?;
+                if self.number_type(value) == Some(VariableType::Decimal) {
+                    return Err(CodeError::new(
+                        value.line,
+                        value.start,
+                        value.end,
+                        "Expected a Zahl but found a Kommazahl, use Runden or AlsZahl to convert it",
+                    ));
+                }
+                self.declare(var_expr, Some(VariableType::Number), constant)?;
+            }
+            ExpressionTypes::DecimalAssignment(var_expr, value) => {
+                self.check_number(value)?;
+                self.declare(var_expr, Some(VariableType::Decimal), constant)?;
+            }
+            ExpressionTypes::ListAssignment(var_expr, value) => {
+                self.check_value(value)?;
+                self.declare(var_expr, Some(VariableType::List), constant)?;
+            }
+            ExpressionTypes::TableAssignment(var_expr, value) => {
+                self.check_value(value)?;
+                self.declare(var_expr, Some(VariableType::Table), constant)?;
+            }
+            ExpressionTypes::Assignment(var_expr, value) => {
+                self.check_not_constant(var_expr)?;
+                self.check_identifier(var_expr)?;
+                self.check_value(value)?;
+                let declaration = self.variables.get(&var_expr.var_name);
+                let old_type = declaration.and_then(|declaration| declaration.var_type.as_ref());
+                if let (true, Some(old_type), Some(new_type)) = (
+                    var_expr.accessors.is_empty(),
+                    old_type,
+                    self.value_type(value),
+                ) {
+                    // A Zahl can always be stored in a Kommazahl
+                    let widened =
+                        *old_type == VariableType::Decimal && new_type == VariableType::Number;
+                    if *old_type != new_type && !widened {
+                        return Err(retype_error(
+                            var_expr,
+                            declaration.unwrap(),
+                            old_type,
+                            &new_type,
+                        ));
+                    }
+                }
+            }
+            ExpressionTypes::ListAppend(var_expr, value)
+            | ExpressionTypes::ListRemove(var_expr, value) => {
+                self.check_not_constant(var_expr)?;
+                self.check_identifier(var_expr)?;
+                self.check_value(value)?;
+            }
+            ExpressionTypes::InputStatement(var_expr, var_type, prompt) => {
+                if let Some(prompt) = prompt {
+                    self.check_text(prompt)?;
+                }
+                self.declare(var_expr, Some(var_type.clone()), false)?;
+            }
+            ExpressionTypes::OutputStatement(values, _) => {
+                for value in values {
+                    self.check_value(value)?;
+                }
+            }
+            ExpressionTypes::ForEachStatement(var_expr, value_var_expr, iterable, body) => {
+                self.check_value(iterable)?;
+                // Only the keys of a Tabelle and the indices of a Liste have a known type
+                let key_type = match self.value_type(iterable) {
+                    Some(VariableType::Table) => Some(VariableType::Text),
+                    Some(VariableType::List) if value_var_expr.is_some() => {
+                        Some(VariableType::Number)
+                    }
+                    _ => None,
+                };
+                self.declare_loop_variable(var_expr, key_type)?;
+                if let Some(value_var_expr) = value_var_expr {
+                    self.declare_loop_variable(value_var_expr, None)?;
+                }
+                self.check(body)?;
+            }
+            ExpressionTypes::StructDeclaration(definition) => {
+                self.declare_struct(definition)?;
+            }
+            ExpressionTypes::RecordAssignment(type_name, var_expr, value) => {
+                if !self.structs.contains_key(&type_name.var_name) {
+                    return Err(unknown_struct_error(&type_name.var_name, type_name));
+                }
+                if let Some(value) = value {
+                    self.check_value(value)?;
+                }
+                self.declare(
+                    var_expr,
+                    Some(VariableType::Record(type_name.var_name.to_owned())),
+                    constant,
+                )?;
+            }
+            ExpressionTypes::ConstantDeclaration(declaration) => {
+                self.check_expression(declaration, true)?;
+            }
+            ExpressionTypes::TryStatement(body, var_expr, handler) => {
+                self.check(body)?;
+                self.declare(
+                    var_expr,
+                    Some(VariableType::Record(ERROR_STRUCT.to_owned())),
+                    false,
+                )?;
+                self.check(handler)?;
+            }
+            ExpressionTypes::ThrowStatement(value) => {
+                self.check_value(value)?;
+            }
+            ExpressionTypes::ReadFile(path, var_expr, lines) => {
+                self.check_value(path)?;
+                let var_type = if *lines {
+                    VariableType::List
+                } else {
+                    VariableType::Text
+                };
+                self.declare(var_expr, Some(var_type), false)?;
+            }
+            ExpressionTypes::WriteFile(value, path, _) => {
+                self.check_value(value)?;
+                self.check_value(path)?;
+            }
+            ExpressionTypes::Import(namespace, file) => {
+                if self.structs.contains_key(&namespace.var_name) {
+                    return Err(namespace_collision_error(namespace));
+                }
+                // Loading the same file again is allowed, it is only run once
+                if self.imports.get(&namespace.var_name) != Some(file) {
+                    // The constants of the file are only known after loading it
+                    self.declare(namespace, None, true)?;
+                    self.imports
+                        .insert(namespace.var_name.to_owned(), file.to_owned());
+                }
+            }
+            ExpressionTypes::ExitStatement(code) => {
+                if let Some(code) = code {
+                    self.check_number(code)?;
+                }
+            }
+            ExpressionTypes::AssertStatement(condition, message) => {
+                self.check_value(condition)?;
+                if let Some(message) = message {
+                    self.check_text(message)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a declaration. Changing a Konstante or the type of a variable is an error,
+    /// declaring it again with the same type is allowed.
+    fn declare(
+        &mut self,
+        var_expr: &IdentifierExpression,
+        var_type: Option<VariableType>,
+        constant: bool,
+    ) -> Result<(), CodeError> {
+        if let Some(existing) = self.variables.get(&var_expr.var_name) {
+            // A declaration inside a loop is checked once but runs again on every iteration
+            let is_same_declaration = existing.var_expr.start == var_expr.start;
+            if !is_same_declaration {
+                self.check_not_constant(var_expr)?;
+                if let (Some(old_type), Some(new_type)) = (&existing.var_type, &var_type) {
+                    if old_type != new_type {
+                        return Err(retype_error(var_expr, existing, old_type, new_type));
+                    }
+                }
+            }
+        }
+        self.variables.insert(
+            var_expr.var_name.to_owned(),
+            Declaration {
+                var_expr: IdentifierExpression::new(
+                    var_expr.line,
+                    var_expr.start,
+                    var_expr.end,
+                    var_expr.var_name.to_owned(),
+                ),
+                var_type,
+                constant,
+            },
+        );
+        Ok(())
+    }
+
+    /// Declares a variable of `Für jedes`. A variable with a known type can only be reused if
+    /// the loop gives values of that type, which is otherwise only known while running.
+    fn declare_loop_variable(
+        &mut self,
+        var_expr: &IdentifierExpression,
+        var_type: Option<VariableType>,
+    ) -> Result<(), CodeError> {
+        if let Some(existing) = self.variables.get(&var_expr.var_name) {
+            let is_same_declaration = existing.var_expr.start == var_expr.start;
+            if let (false, None, Some(old_type)) =
+                (is_same_declaration, &var_type, &existing.var_type)
+            {
+                self.check_not_constant(var_expr)?;
+                return Err(loop_variable_error(var_expr, existing, old_type));
+            }
+        }
+        self.declare(var_expr, var_type, false)
+    }
+
+    /// Type of a value as far as it is known without running the script.
+    fn value_type(&self, v_expr: &ValueExpression) -> Option<VariableType> {
+        match v_expr.get_expression() {
+            ValueExpressionTypes::Identifier(var_expr) if var_expr.accessors.is_empty() => self
+                .variables
+                .get(&var_expr.var_name)
+                .and_then(|declaration| declaration.var_type.clone()),
+            ValueExpressionTypes::Identifier(_) => None,
+            ValueExpressionTypes::Call(call) if self.structs.contains_key(&call.name.var_name) => {
+                Some(VariableType::Record(call.name.var_name.to_owned()))
+            }
+            ValueExpressionTypes::Call(_) => None,
+            ValueExpressionTypes::Number(n_expr) => self.number_type(n_expr),
+            ValueExpressionTypes::Text(_) => Some(VariableType::Text),
+            ValueExpressionTypes::List(_) => Some(VariableType::List),
+            ValueExpressionTypes::Table(_) => Some(VariableType::Table),
+        }
+    }
+
+    /// Whether a calculation results in a Zahl or a Kommazahl, if it is known before running.
+    fn number_type(&self, n_expr: &NumberExpression) -> Option<VariableType> {
+        match n_expr.get_expression() {
+            NumberExpressionTypes::Value(_)
+            | NumberExpressionTypes::Contains(..)
+            | NumberExpressionTypes::Compare(..) => Some(VariableType::Number),
+            NumberExpressionTypes::Decimal(_) => Some(VariableType::Decimal),
+            NumberExpressionTypes::Identifier(var_expr) if var_expr.accessors.is_empty() => self
+                .variables
+                .get(&var_expr.var_name)
+                .and_then(|declaration| declaration.var_type.clone()),
+            NumberExpressionTypes::Identifier(_) | NumberExpressionTypes::Call(_) => None,
+            NumberExpressionTypes::Add(lhs, rhs)
+            | NumberExpressionTypes::Sub(lhs, rhs)
+            | NumberExpressionTypes::Mult(lhs, rhs)
+            | NumberExpressionTypes::Div(lhs, rhs) => {
+                match (self.number_type(lhs), self.number_type(rhs)) {
+                    (Some(VariableType::Decimal), _) | (_, Some(VariableType::Decimal)) => {
+                        Some(VariableType::Decimal)
+                    }
+                    (Some(VariableType::Number), Some(VariableType::Number)) => {
+                        Some(VariableType::Number)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn check_not_constant(&self, var_expr: &IdentifierExpression) -> Result<(), CodeError> {
+        match self.variables.get(&var_expr.var_name) {
+            Some(existing) if existing.constant => {
+                Err(constant_error(var_expr, &existing.var_expr))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn declare_struct(&mut self, definition: &StructDefinition) -> Result<(), CodeError> {
+        let name = &definition.name;
+        if self.structs.contains_key(&name.var_name) {
+            return Err(CodeError::new(
+                name.line,
+                name.start,
+                name.end,
+                format!("Struktur {} is already declared", name.var_name).as_str(),
+            ));
+        }
+        if self.imports.contains_key(&name.var_name) {
+            return Err(namespace_collision_error(name));
+        }
+        let mut fields: StructFields = Vec::new();
+        for field in &definition.fields {
+            if fields.iter().any(|(name, _)| *name == field.name.var_name) {
+                return Err(CodeError::new(
+                    field.name.line,
+                    field.name.start,
+                    field.name.end,
+                    format!("Field {} is declared twice", field.name.var_name).as_str(),
+                ));
+            }
+            fields.push((field.name.var_name.to_owned(), field.field_type.clone()));
+        }
+        self.structs.insert(name.var_name.to_owned(), fields);
+        Ok(())
+    }
+
+    /// Checks the accessors of a variable and that every accessed field exists.
+    fn check_identifier(&mut self, var_expr: &IdentifierExpression) -> Result<(), CodeError> {
+        let mut var_type = self
+            .variables
+            .get(&var_expr.var_name)
+            .and_then(|declaration| declaration.var_type.clone());
+        for accessor in &var_expr.accessors {
+            var_type = match accessor {
+                Accessor::Index(index) => {
+                    self.check_value(index)?;
+                    None
+                }
+                Accessor::Slice(from, to) => {
+                    for bound in [from, to].into_iter().flatten() {
+                        self.check_value(bound)?;
+                    }
+                    var_type
+                }
+                Accessor::Field(field) => match var_type {
+                    Some(VariableType::Record(name)) => {
+                        let fields = self
+                            .structs
+                            .get(&name)
+                            .ok_or_else(|| unknown_struct_error(&name, field))?;
+                        match fields.iter().find(|(name, _)| *name == field.var_name) {
+                            Some((_, field_type)) => Some(field_type.clone()),
+                            None => return Err(unknown_field_error(&name, fields, field)),
+                        }
+                    }
+                    Some(other) => {
+                        return Err(CodeError::new(
+                            var_expr.line,
+                            var_expr.start,
+                            var_expr.end,
+                            format!(
+                                "Only a Struktur has fields, but {} is a {}",
+                                var_expr.var_name,
+                                other.name()
+                            )
+                            .as_str(),
+                        ))
+                    }
+                    None => None,
+                },
+            };
+        }
+        Ok(())
+    }
+
+    fn check_call(&mut self, call: &FunctionCall) -> Result<(), CodeError> {
+        for arg in &call.args {
+            self.check_value(arg)?;
+        }
+        if let Some(fields) = self.structs.get(&call.name.var_name) {
+            if fields.len() != call.args.len() {
+                return Err(CodeError::new(
+                    call.line,
+                    call.start,
+                    call.end,
+                    format!(
+                        "{} has {} fields but got {} values",
+                        call.name.var_name,
+                        fields.len(),
+                        call.args.len()
+                    )
+                    .as_str(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_value(&mut self, v_expr: &ValueExpression) -> Result<(), CodeError> {
+        match v_expr.get_expression() {
+            ValueExpressionTypes::Identifier(var_expr) => self.check_identifier(var_expr),
+            ValueExpressionTypes::Call(call) => self.check_call(call),
+            ValueExpressionTypes::Number(n_expr) => self.check_number(n_expr),
+            ValueExpressionTypes::Text(t_expr) => self.check_text(t_expr),
+            ValueExpressionTypes::List(items) => {
+                for item in items {
+                    self.check_value(item)?;
+                }
+                Ok(())
+            }
+            ValueExpressionTypes::Table(entries) => {
+                for (key, value) in entries {
+                    self.check_value(key)?;
+                    self.check_value(value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check_number(&mut self, n_expr: &NumberExpression) -> Result<(), CodeError> {
+        match n_expr.get_expression() {
+            NumberExpressionTypes::Value(_) | NumberExpressionTypes::Decimal(_) => Ok(()),
+            NumberExpressionTypes::Identifier(var_expr) => self.check_identifier(var_expr),
+            NumberExpressionTypes::Add(lhs, rhs)
+            | NumberExpressionTypes::Sub(lhs, rhs)
+            | NumberExpressionTypes::Mult(lhs, rhs)
+            | NumberExpressionTypes::Div(lhs, rhs) => {
+                self.check_number(lhs)?;
+                self.check_number(rhs)
+            }
+            NumberExpressionTypes::Call(call) => self.check_call(call),
+            NumberExpressionTypes::Contains(lhs, rhs)
+            | NumberExpressionTypes::Compare(_, lhs, rhs) => {
+                self.check_value(lhs)?;
+                self.check_value(rhs)
+            }
+        }
+    }
+
+    fn check_text(&mut self, t_expr: &TextExpression) -> Result<(), CodeError> {
+        match t_expr.get_expression() {
+            TextExpressionTypes::Concat(lhs, rhs) => {
+                self.check_text(lhs)?;
+                self.check_text(rhs)
+            }
+            TextExpressionTypes::Value(_) => Ok(()),
+            TextExpressionTypes::Identifier(var_expr) => self.check_identifier(var_expr),
+            TextExpressionTypes::Number(n_expr) => self.check_number(n_expr),
+            TextExpressionTypes::Formatted(t_expr, _) => self.check_text(t_expr),
+            TextExpressionTypes::Call(call) => self.check_call(call),
+        }
+    }
+}
+
+fn retype_error(
+    var_expr: &IdentifierExpression,
+    existing: &Declaration,
+    old_type: &VariableType,
+    new_type: &VariableType,
+) -> CodeError {
+    let old = &existing.var_expr;
+    CodeError::new(
+        var_expr.line,
+        var_expr.start,
+        var_expr.end,
+        format!(
+            "{} is a {} and can not become a {}",
+            var_expr.var_name,
+            old_type.name(),
+            new_type.name()
+        )
+        .as_str(),
+    )
+    .with_note(
+        old.line,
+        old.start,
+        old.end,
+        format!("{} was declared as {} here", old.var_name, old_type.name()).as_str(),
+    )
+}
+
+fn loop_variable_error(
+    var_expr: &IdentifierExpression,
+    existing: &Declaration,
+    old_type: &VariableType,
+) -> CodeError {
+    let old = &existing.var_expr;
+    CodeError::new(
+        var_expr.line,
+        var_expr.start,
+        var_expr.end,
+        format!(
+            "{} is a {} and can not be used for the values of Für jedes",
+            var_expr.var_name,
+            old_type.name()
+        )
+        .as_str(),
+    )
+    .with_note(
+        old.line,
+        old.start,
+        old.end,
+        format!("{} was declared as {} here", old.var_name, old_type.name()).as_str(),
+    )
+    .with_help("Use a new name for the loop variable")
+}
+
+/// Error for changing a variable declared with `Konstante` or `Lade`.
+pub fn constant_error(
+    var_expr: &IdentifierExpression,
+    declared: &IdentifierExpression,
+) -> CodeError {
+    let error = CodeError::new(
+        var_expr.line,
+        var_expr.start,
+        var_expr.end,
+        format!(
+            "{} is a Konstante and can not be changed",
+            var_expr.var_name
+        )
+        .as_str(),
+    );
+    if *declared == args_declaration() {
+        return error.with_help("argumente holds the arguments after `--` on the command line");
+    }
+    error.with_note(
+        declared.line,
+        declared.start,
+        declared.end,
+        format!("{} was declared as Konstante here", declared.var_name).as_str(),
+    )
+}
+
+/// Error for a `Lade` namespace and a `Struktur` with the same name.
+pub fn namespace_collision_error(name: &IdentifierExpression) -> CodeError {
+    CodeError::new(
+        name.line,
+        name.start,
+        name.end,
+        format!(
+            "{} is both a Struktur and the name of a file loaded with Lade",
+            name.var_name
+        )
+        .as_str(),
+    )
+    .with_help("Rename the Struktur or the file")
+}
+
+/// Error for a type name or record that is not a declared `Struktur`.
+pub fn unknown_struct_error(struct_name: &str, at: &IdentifierExpression) -> CodeError {
+    CodeError::new(
+        at.line,
+        at.start,
+        at.end,
+        format!("{} is not a known Struktur", struct_name).as_str(),
+    )
+}
+
+/// Error for accessing a field a `Struktur` does not have, listing the fields it has.
+pub fn unknown_field_error(
+    struct_name: &str,
+    fields: &StructFields,
+    field: &IdentifierExpression,
+) -> CodeError {
+    let available: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+    CodeError::new(
+        field.line,
+        field.start,
+        field.end,
+        format!(
+            "{} has no field {}. Available fields: {}",
+            struct_name,
+            field.var_name,
+            available.join(", ")
+        )
+        .as_str(),
+    )
+    .with_suggestion(&field.var_name, available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypeChecker;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn check(source: &str) -> Result<(), String> {
+        // Like `red`, which adds a line break to the end of the file
+        let tokens = Lexer::new(&format!("{}\n", source)).lex().unwrap();
+        let parsed = Parser::new(tokens).parse().unwrap();
+        TypeChecker::new()
+            .check(&parsed)
+            .map_err(|err| err.msg().to_owned())
+    }
+
+    #[test]
+    fn loop_variables_keep_their_type() {
+        assert_eq!(
+            check("Zahl x = 1\nFür jedes x in [\"a\"]\nEnde\n"),
+            Err("x is a Zahl and can not be used for the values of Für jedes".to_owned())
+        );
+        assert_eq!(
+            check("Zahl x = 1\nTabelle t = {a: 1}\nFür jedes x in t\nEnde\n"),
+            Err("x is a Zahl and can not become a Text".to_owned())
+        );
+    }
+
+    #[test]
+    fn loop_variables_can_be_reused() {
+        let source = "Für jedes x in [1]\nEnde\nFür jedes x in [2]\nEnde\n";
+        assert_eq!(check(source), Ok(()));
+        let source = "Text k = \"a\"\nTabelle t = {a: 1}\nFür jedes k in t\nEnde\n";
+        assert_eq!(check(source), Ok(()));
+        let source = "Zahl i = 0\nFür jedes i, wert in [5]\nEnde\n";
+        assert_eq!(check(source), Ok(()));
+    }
+
+    #[test]
+    fn arguments_can_not_be_changed() {
+        for source in [
+            "Zahl argumente = 1\n",
+            "argumente = [1]\n",
+            "Liste argumente\n",
+        ] {
+            assert_eq!(
+                check(source),
+                Err("argumente is a Konstante and can not be changed".to_owned()),
+                "{}",
+                source
+            );
+        }
+        assert_eq!(check("Output Länge(argumente)\n"), Ok(()));
+    }
+}
@@ -0,0 +1,2 @@
+mod checker;
+pub use checker::*;
@@ -1,4 +1,6 @@
-use colored::Colorize;
+use std::fmt::Write;
+
+use colored::{Color, Colorize};
 
 #[derive(Debug)]
 pub struct CodeError {
@@ -6,7 +8,34 @@ pub struct CodeError {
     start: usize,
     end: usize,
     msg: String,
+    notes: Vec<Note>,
+    /// Hints shown below the code, e.g. a suggestion for a misspelled name
+    help: Vec<String>,
+    /// Name and content of the file the error is in, if it is not the main script. Boxed to
+    /// keep errors small, as they are returned everywhere
+    file: Option<Box<(String, String)>>,
+    /// Stage that found the error if it differs from where it is reported, e.g. a parser
+    /// error in a file loaded with `Lade` while running
+    stage: Option<Stage>,
+}
+
+/// Part of the interpreter that finds an error, each one has its own exit code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Lexer,
+    Parser,
+    TypeCheck,
 }
+
+/// Additional place in the code that helps to understand an error.
+#[derive(Debug)]
+struct Note {
+    line: usize,
+    start: usize,
+    end: usize,
+    msg: String,
+}
+
 impl CodeError {
     pub fn new(line: usize, start: usize, end: usize, msg: &str) -> Self {
         CodeError {
@@ -14,68 +43,249 @@ impl CodeError {
             start,
             end,
             msg: msg.to_owned(),
+            notes: Vec::new(),
+            help: Vec::new(),
+            file: None,
+            stage: None,
+        }
+    }
+    /// Adds a note pointing at another place in the code, e.g. the original declaration.
+    pub fn with_note(mut self, line: usize, start: usize, end: usize, msg: &str) -> Self {
+        self.notes.push(Note {
+            line,
+            start,
+            end,
+            msg: msg.to_owned(),
+        });
+        self
+    }
+    /// Adds a hint that is shown below the code.
+    pub fn with_help(mut self, msg: &str) -> Self {
+        self.help.push(msg.to_owned());
+        self
+    }
+    /// Adds "Did you mean ...?" if one of the `candidates` is similar to the misspelled `name`.
+    pub fn with_suggestion<'a>(
+        self,
+        name: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        match did_you_mean(name, candidates) {
+            Some(suggestion) => self.with_help(format!("Did you mean {}?", suggestion).as_str()),
+            None => self,
+        }
+    }
+    /// Marks the error as coming from another file, e.g. one loaded with `Lade`. An error
+    /// keeps the innermost file it was found in.
+    pub fn in_file(mut self, name: &str, input: &str) -> Self {
+        if self.file.is_none() {
+            self.file = Some(Box::new((name.to_owned(), input.to_owned())));
+        }
+        self
+    }
+    /// Marks the stage that found the error, an error keeps the first stage it was marked with.
+    pub fn in_stage(mut self, stage: Stage) -> Self {
+        self.stage.get_or_insert(stage);
+        self
+    }
+    pub fn stage(&self) -> Option<Stage> {
+        self.stage
+    }
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+    pub fn help(&self) -> &[String] {
+        &self.help
+    }
+    pub fn print_error(&self, input: &str) {
+        print!("{}", self.render(input));
+    }
+    /// Returns the error with the affected code as it is printed by `print_error`.
+    pub fn render(&self, input: &str) -> String {
+        self.render_colored(input, Color::Red)
+    }
+    /// Like `render`, but in yellow for a warning that does not stop the script.
+    pub fn render_warning(&self, input: &str) -> String {
+        self.render_colored(input, Color::Yellow)
+    }
+    fn render_colored(&self, input: &str, color: Color) -> String {
+        let mut rendered = String::new();
+        let input = match self.file.as_deref() {
+            Some((name, file_input)) => {
+                let _ = writeln!(rendered, "{} {}:{}", "-->".cyan(), name, self.line);
+                file_input
+            }
+            None => input,
+        };
+        rendered.push_str(&render_snippet(
+            input, self.line, self.start, self.end, &self.msg, color,
+        ));
+        for note in &self.notes {
+            rendered.push_str(&render_snippet(
+                input,
+                note.line,
+                note.start,
+                note.end,
+                &note.msg,
+                Color::Cyan,
+            ));
+        }
+        for help in &self.help {
+            let _ = writeln!(rendered, "{} {}", "help:".cyan().bold(), help);
         }
+        rendered
     }
-    pub fn print_error(&self, input: &String) {
-        let lines: Vec<&str> = input.split("\n").collect();
-        if let Some(line) = lines.get(self.line - 1) {
-            let lines_char_count = lines
-                .iter()
-                .take(self.line - 1)
-                .map(|s| s.chars().count() + 1)
-                .sum::<usize>();
+}
 
-            let start_col = if self.start < lines_char_count {
-                self.start
-            } else {
-                self.start - lines_char_count
-            };
-            let end_col = start_col + (self.end - self.start);
+fn render_snippet(
+    input: &str,
+    line_nr: usize,
+    start: usize,
+    end: usize,
+    msg: &str,
+    color: Color,
+) -> String {
+    let mut out = String::new();
+    let lines: Vec<&str> = input.split("\n").collect();
+    if let Some(line) = lines.get(line_nr - 1) {
+        let lines_char_count = lines
+            .iter()
+            .take(line_nr - 1)
+            .map(|s| s.chars().count() + 1)
+            .sum::<usize>();
 
-            println!("{} | ", " ".repeat(self.line.to_string().len()));
-            print!("{} | ", self.line.to_string().yellow());
-            print!(
-                "{}",
-                line.chars()
-                    .skip(0)
-                    .take(start_col)
-                    .collect::<String>()
-                    .green()
-            );
-            print!(
-                "{}",
-                line.chars()
-                    .skip(start_col)
-                    .take(end_col - start_col)
-                    .collect::<String>()
-                    .red()
-                    .bold()
-            );
-            println!(
+        let start_col = if start < lines_char_count {
+            start
+        } else {
+            start - lines_char_count
+        };
+        // Spans of blocks like `Für jedes` reach into later lines, only the first one is shown
+        let end_col = (start_col + (end - start))
+            .min(line.chars().count() + 1)
+            .max(start_col);
+
+        let _ = writeln!(out, "{} | ", " ".repeat(line_nr.to_string().len()));
+        let _ = write!(out, "{} | ", line_nr.to_string().yellow());
+        let _ = write!(
+            out,
+            "{}",
+            line.chars().take(start_col).collect::<String>().green()
+        );
+        let _ = write!(
+            out,
+            "{}",
+            line.chars()
+                .skip(start_col)
+                .take(end_col - start_col)
+                .collect::<String>()
+                .color(color)
+                .bold()
+        );
+        let _ = writeln!(
+            out,
+            "{}",
+            line.chars()
+                .skip(end_col)
+                .take(line.len() + 1)
+                .collect::<String>()
+                .yellow()
+        );
+        let _ = write!(out, "{} | ", " ".repeat(line_nr.to_string().len()));
+        out.push_str(&" ".repeat(start_col));
+        for i in start_col..end_col {
+            let _ = write!(
+                out,
                 "{}",
-                line.chars()
-                    .skip(end_col)
-                    .take(line.len() + 1)
-                    .collect::<String>()
-                    .yellow()
+                (if i == start_col || i == end_col + 1 {
+                    "^"
+                } else {
+                    "~"
+                })
+                .color(color)
             );
-            print!("{} | ", " ".repeat(self.line.to_string().len()));
-            print!("{}", " ".repeat(start_col));
-            for i in start_col..end_col {
-                print!(
-                    "{}",
-                    (if i == start_col || i == end_col + 1 {
-                        "^"
-                    } else {
-                        "~"
-                    })
-                    .red()
-                );
+        }
+        let _ = writeln!(out, " {}", msg.color(color));
+    } else {
+        let _ = writeln!(out, "Error on line {}!", line_nr);
+        let _ = writeln!(out, "{}", msg);
+    }
+    out
+}
+
+/// Returns the candidate that is most similar to `name`, if any is similar enough to be a
+/// typo. Differences in upper and lower case are not counted, so `text` suggests `Text`.
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let lowercase = name.to_lowercase();
+    // Longer names may have more typos, e.g. one for `nmae` and two for `Ouptut`. A name must
+    // keep at least one character, otherwise `x` would suggest every other short name
+    let length = name.chars().count();
+    let max_distance = (length / 3).max(1).min(length.saturating_sub(1));
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| {
+            (
+                edit_distance(&lowercase, &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Number of inserted, removed, replaced or swapped characters to get from `a` to `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // distances[i][j] is the distance between the first i characters of a and j of b
+    let mut distances = vec![vec![0_usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
             }
-            println!(" {}", self.msg.red());
-        } else {
-            println!("Error on line {}!", self.line);
-            println!("{}", self.msg);
+            distances[i][j] = distance;
         }
     }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::did_you_mean;
+
+    #[test]
+    fn suggests_similar_names() {
+        assert_eq!(did_you_mean("nmae", ["name", "alter"]), Some("name"));
+        assert_eq!(did_you_mean("Ouptut", ["Output", "Input"]), Some("Output"));
+        assert_eq!(did_you_mean("text", ["Text"]), Some("Text"));
+        assert_eq!(did_you_mean("ab", ["ac"]), Some("ac"));
+    }
+
+    #[test]
+    fn short_names_need_a_common_character() {
+        assert_eq!(did_you_mean("x", ["y", "z"]), None);
+        assert_eq!(did_you_mean("x", ["X"]), Some("X"));
+        assert_eq!(did_you_mean("ab", ["xy"]), None);
+    }
 }
@@ -0,0 +1,319 @@
+use std::{fs, path::PathBuf};
+
+use colored::Colorize;
+
+use crate::{
+    error::CodeError,
+    lexer::{Lexer, Token, TokenType, KEYWORDS},
+    test_runner::diff,
+};
+
+const INDENT: &str = "    ";
+
+/// Formats every file in place. With `check` the files are left unchanged and the ones that
+/// are not formatted are shown with the difference. Returns whether all files were formatted
+/// (or already were with `check`).
+pub fn format_files(files: &[PathBuf], check: bool) -> bool {
+    let mut success = true;
+    for file in files {
+        let Ok(content) = fs::read_to_string(file) else {
+            println!("Error while reading file '{}'", file.display());
+            success = false;
+            continue;
+        };
+        let source = content.replace("\r", "");
+        let formatted = match format(&source) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                let name = file.display().to_string();
+                print!("{}", err.in_file(&name, &source).render(&source));
+                success = false;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{} {}", "Not formatted".red().bold(), file.display());
+            print!("{}", diff(&source, &formatted));
+            success = false;
+        } else if let Err(err) = fs::write(file, &formatted) {
+            println!("Error while writing file '{}': {}", file.display(), err);
+            success = false;
+        } else {
+            println!("Formatted {}", file.display());
+        }
+    }
+    success
+}
+
+/// Returns `source` in the canonical format: keywords written as in the documentation, one
+/// space around operators and after commas, blocks indented by four spaces, at most one
+/// blank line in a row and comments kept where they are.
+pub fn format(source: &str) -> Result<String, CodeError> {
+    let mut source = normalize_keywords(source);
+    if !source.ends_with('\n') {
+        source.push('\n');
+    }
+    let tokens = Lexer::new(&source).lex()?;
+    let chars: Vec<char> = source.chars().collect();
+    let lines: Vec<&str> = source.split('\n').collect();
+
+    let mut out = String::new();
+    let mut depth = 0_usize;
+    let mut blank_line = false;
+    for statement in tokens.split(|token| token.token_type == TokenType::NEWLINE) {
+        let statement = match statement.last() {
+            Some(token) if token.token_type == TokenType::EOF => &statement[..statement.len() - 1],
+            _ => statement,
+        };
+        let Some(first) = statement.first() else {
+            // Blank lines at the start of the file are dropped, several in a row become one
+            blank_line = !out.is_empty();
+            continue;
+        };
+        if blank_line {
+            out.push('\n');
+            blank_line = false;
+        }
+        if matches!(first.token_type, TokenType::END | TokenType::CATCH) {
+            depth = depth.saturating_sub(1);
+        }
+        out.push_str(&INDENT.repeat(depth));
+        format_statement(statement, &chars, &lines, depth, &mut out);
+        out.push('\n');
+        if matches!(
+            first.token_type,
+            TokenType::FOR | TokenType::TRY | TokenType::CATCH
+        ) {
+            depth += 1;
+        }
+    }
+
+    // The formatter only changes whitespace, anything else would be a bug in it
+    let formatted_tokens = Lexer::new(&out).lex()?;
+    let meaningful = |token: &&Token| {
+        !matches!(
+            token.token_type,
+            TokenType::NEWLINE | TokenType::COMMENT | TokenType::EOF
+        )
+    };
+    let mut formatted_tokens = formatted_tokens.iter().filter(meaningful);
+    for token in tokens.iter().filter(meaningful) {
+        let same = formatted_tokens.next().is_some_and(|formatted| {
+            formatted.token_type == token.token_type && formatted.value == token.value
+        });
+        if !same {
+            return Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Formatting would change the meaning of this line, the file was left unchanged",
+            ));
+        }
+    }
+    Ok(out)
+}
+
+/// Writes the tokens of a single line. Whitespace between tokens is replaced by the canonical
+/// spacing, everything else between them (like the `$` around variables in texts) is kept.
+fn format_statement(
+    statement: &[Token],
+    chars: &[char],
+    lines: &[&str],
+    depth: usize,
+    out: &mut String,
+) {
+    let slice = |start: usize, end: usize| chars[start..end.max(start)].iter().collect::<String>();
+    let mut open_brackets = Vec::new();
+    let mut prev: Option<(&Token, bool)> = None;
+    let mut tokens = statement.iter();
+    while let Some(token) = tokens.next() {
+        if let Some((prev_token, prev_unary)) = prev {
+            let gap = slice(prev_token.end, token.start);
+            if gap.contains("\"\"\"") {
+                multiline_text(prev_token.line, lines, depth, out);
+                // The rest of the statement is the text itself, only a comment can follow it
+                if let Some(comment) = tokens.find(|token| token.token_type == TokenType::COMMENT) {
+                    out.push(' ');
+                    out.push_str(slice(comment.start, comment.end).trim_end());
+                }
+                return;
+            }
+            if gap.trim().is_empty() {
+                let top = open_brackets.last();
+                out.push_str(spacing(prev_token, prev_unary, token, top, gap.is_empty()));
+            } else {
+                out.push_str(&collapse_whitespace(&gap));
+            }
+        }
+        let text = slice(token.start, token.end);
+        out.push_str(match token.token_type {
+            TokenType::COMMENT => text.trim_end(),
+            _ => &text,
+        });
+
+        match token.token_type {
+            TokenType::LPAREN | TokenType::LBRACKET | TokenType::LBRACE => {
+                open_brackets.push(token.token_type.clone());
+            }
+            TokenType::RPAREN | TokenType::RBRACKET | TokenType::RBRACE => {
+                open_brackets.pop();
+            }
+            _ => {}
+        }
+        let unary = token.token_type == TokenType::SUB
+            && !prev.is_some_and(|(prev_token, _)| ends_operand(prev_token));
+        prev = Some((token, unary));
+    }
+}
+
+/// Spacing between two tokens that only have whitespace between them in the source.
+fn spacing(
+    prev: &Token,
+    prev_unary: bool,
+    next: &Token,
+    open_bracket: Option<&TokenType>,
+    touching: bool,
+) -> &'static str {
+    use TokenType::*;
+    if next.token_type == COMMENT {
+        return " ";
+    }
+    if prev_unary {
+        return "";
+    }
+    match (&prev.token_type, &next.token_type) {
+        (COMMA, _) => " ",
+        (_, COMMA | COLON | DOT | RPAREN | RBRACKET | RBRACE) => "",
+        (LPAREN | LBRACKET | LBRACE | DOT, _) => "",
+        // `[1:3]` slices a list, `{a: 1}` is a table and `Struktur Punkt: Zahl x` a declaration
+        (COLON, _) if open_bracket == Some(&LBRACKET) => "",
+        (COLON, _) => " ",
+        _ if is_operator(prev) || is_operator(next) => " ",
+        _ if touching => "",
+        _ => " ",
+    }
+}
+
+fn is_operator(token: &Token) -> bool {
+    use TokenType::*;
+    matches!(
+        token.token_type,
+        ADD | SUB
+            | MULT
+            | DIV
+            | CONCAT
+            | EQUAL
+            | ADDEQUAL
+            | SUBEQUAL
+            | MULTEQUAL
+            | DIVEQUAL
+            | EQUALEQUAL
+            | NOTEQUAL
+            | LESS
+            | LESSEQUAL
+            | GREATER
+            | GREATEREQUAL
+    )
+}
+
+/// Whether a `-` after this token subtracts, otherwise it negates the following value.
+fn ends_operand(token: &Token) -> bool {
+    use TokenType::*;
+    matches!(
+        token.token_type,
+        VALUE | IDENTIFIER | QUOTE | RPAREN | RBRACKET | RBRACE
+    )
+}
+
+/// Replaces every run of whitespace by a single space, e.g. in `Für   jedes  x`.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.starts_with(char::is_whitespace) {
+        collapsed.insert(0, ' ');
+    }
+    if text.ends_with(char::is_whitespace) {
+        collapsed.push(' ');
+    }
+    collapsed
+}
+
+/// Writes a text between `"""` lines that starts after `open_line`. The lines are indented one
+/// level deeper than the statement, their indentation relative to each other is kept.
+fn multiline_text(open_line: usize, lines: &[&str], depth: usize, out: &mut String) {
+    let content: Vec<&str> = lines[open_line..]
+        .iter()
+        .take_while(|line| line.trim() != "\"\"\"")
+        .copied()
+        .collect();
+    let leading = |line: &str| line.chars().take_while(|ch| ch.is_whitespace()).count();
+    let common = content
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading(line))
+        .min()
+        .unwrap_or(0);
+    let indent = INDENT.repeat(depth + 1);
+    out.push_str(" \"\"\"");
+    for line in content {
+        let rest: String = line.chars().skip(common.min(leading(line))).collect();
+        out.push('\n');
+        if !rest.is_empty() {
+            out.push_str(&indent);
+            out.push_str(&rest);
+        }
+    }
+    out.push('\n');
+    out.push_str(&indent);
+    out.push_str("\"\"\"");
+}
+
+/// Writes keywords at the start of a line as in the documentation, e.g. `output` as `Output`.
+/// Lines of texts between `"""` lines are kept as they are.
+fn normalize_keywords(source: &str) -> String {
+    let mut in_text = false;
+    let mut lines = Vec::new();
+    for line in source.split('\n') {
+        let trimmed = line.trim();
+        if in_text {
+            in_text = trimmed != "\"\"\"";
+            lines.push(line.to_owned());
+            continue;
+        }
+        let normalized = normalize_line(line);
+        in_text = trimmed.ends_with("\"\"\"") && trimmed != "\"\"\"";
+        lines.push(normalized);
+    }
+    lines.join("\n")
+}
+
+fn normalize_line(line: &str) -> String {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+    let word_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let (word, after) = rest.split_at(word_len);
+    let Some(keyword) = KEYWORDS
+        .iter()
+        .find(|keyword| keyword.to_lowercase() == word.to_lowercase())
+    else {
+        return line.to_owned();
+    };
+    // A variable with the name of a keyword can still be changed, e.g. `ende = 1`
+    let is_assignment = ["=", "+=", "-=", "*=", "/="]
+        .iter()
+        .any(|operator| after.trim_start().starts_with(operator));
+    if is_assignment {
+        return line.to_owned();
+    }
+    let after = match *keyword {
+        "Konstante" => normalize_line(after),
+        "Für" => match after.trim_start().get(..5) {
+            Some(jedes) if jedes.eq_ignore_ascii_case("jedes") => after.replacen(jedes, "jedes", 1),
+            _ => after.to_owned(),
+        },
+        _ => after.to_owned(),
+    };
+    format!("{}{}{}", indent, keyword, after)
+}
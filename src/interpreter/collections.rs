@@ -0,0 +1,469 @@
+use crate::{
+    checker::{unknown_field_error, unknown_struct_error},
+    error::CodeError,
+    parser::{
+        Accessor, Expression, IdentifierExpression, ValueExpression, ValueExpressionTypes,
+        VariableType,
+    },
+};
+
+use super::{parse_number, Interpreter, RuntimeTypes};
+
+impl Interpreter {
+    /// Returns the value of a variable after applying its index and slice accessors.
+    pub fn eval_identifier(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+    ) -> Result<RuntimeTypes, CodeError> {
+        let mut value = self.get_var(var_expr, &var_expr.var_name)?;
+        for accessor in &var_expr.accessors {
+            value = match (value, accessor) {
+                (RuntimeTypes::List(items), Accessor::Index(index)) => {
+                    let idx = self.eval_index(expr, index, items.len(), false)?;
+                    items.into_iter().nth(idx).unwrap()
+                }
+                (RuntimeTypes::List(items), Accessor::Slice(from, to)) => {
+                    let from_idx = match from {
+                        Some(from) => self.eval_index(expr, from, items.len(), true)?,
+                        None => 0,
+                    };
+                    let to_idx = match to {
+                        Some(to) => self.eval_index(expr, to, items.len(), true)?,
+                        None => items.len(),
+                    };
+                    if from_idx > to_idx {
+                        return Err(CodeError::new(
+                            var_expr.line,
+                            var_expr.start,
+                            var_expr.end,
+                            format!(
+                                "Slice start {} is greater than its end {}",
+                                from_idx, to_idx
+                            )
+                            .as_str(),
+                        ));
+                    }
+                    RuntimeTypes::List(items[from_idx..to_idx].to_vec())
+                }
+                (RuntimeTypes::Table(mut table), Accessor::Index(key_expr)) => {
+                    let key = self.eval_key(expr, key_expr)?;
+                    table
+                        .remove(&key)
+                        .ok_or_else(|| missing_key_error(key_expr, &key))?
+                }
+                (RuntimeTypes::Table(_), Accessor::Slice(..)) => {
+                    return Err(CodeError::new(
+                        var_expr.line,
+                        var_expr.start,
+                        var_expr.end,
+                        "A Tabelle can not be sliced",
+                    ))
+                }
+                (RuntimeTypes::Record(name, fields), Accessor::Field(field)) => {
+                    match fields.into_iter().find(|(name, _)| *name == field.var_name) {
+                        Some((_, value)) => value,
+                        None => {
+                            let fields = self.struct_fields(&name, field)?;
+                            return Err(unknown_field_error(&name, fields, field));
+                        }
+                    }
+                }
+                (other, Accessor::Field(_)) => return Err(no_fields_error(var_expr, &other)),
+                (other, _) => return Err(not_indexable_error(var_expr, &other)),
+            };
+        }
+        Ok(value)
+    }
+
+    /// Evaluates a list index and checks that it lies within a Liste of `len` elements.
+    /// Slice bounds may also point directly behind the last element.
+    fn eval_index(
+        &mut self,
+        expr: &Expression,
+        index: &ValueExpression,
+        len: usize,
+        is_slice_bound: bool,
+    ) -> Result<usize, CodeError> {
+        let value = self.eval_value_expression(expr, index)?;
+        let idx = match &value {
+            RuntimeTypes::Number(idx) => *idx,
+            RuntimeTypes::String(text) => parse_number(text).map_err(|reason| {
+                CodeError::new(
+                    index.line,
+                    index.start,
+                    index.end,
+                    format!("Expected a Zahl as index but {}", reason).as_str(),
+                )
+            })?,
+            _ => {
+                return Err(CodeError::new(
+                    index.line,
+                    index.start,
+                    index.end,
+                    format!("Expected a Zahl as index but found a {}", value.type_name()).as_str(),
+                ))
+            }
+        };
+        let max = if is_slice_bound {
+            len
+        } else {
+            len.wrapping_sub(1)
+        };
+        if idx < 0 || len == 0 && !is_slice_bound || idx as usize > max {
+            return Err(CodeError::new(
+                index.line,
+                index.start,
+                index.end,
+                format!(
+                    "Index {} is out of range, the Liste has {} elements",
+                    idx, len
+                )
+                .as_str(),
+            ));
+        }
+        Ok(idx as usize)
+    }
+
+    /// Evaluates a Tabelle key or the value searched by `enthält`. A bare word that is not
+    /// the name of a variable is used as text itself, so `preise[apfel]` looks up "apfel".
+    fn eval_key_value(
+        &mut self,
+        expr: &Expression,
+        v_expr: &ValueExpression,
+    ) -> Result<RuntimeTypes, CodeError> {
+        if let ValueExpressionTypes::Identifier(var_expr) = v_expr.get_expression() {
+            if var_expr.accessors.is_empty() && !self.variables.contains_key(&var_expr.var_name) {
+                return Ok(RuntimeTypes::String(var_expr.var_name.to_owned()));
+            }
+        }
+        self.eval_value_expression(expr, v_expr)
+    }
+
+    pub fn eval_key(
+        &mut self,
+        expr: &Expression,
+        v_expr: &ValueExpression,
+    ) -> Result<String, CodeError> {
+        match self.eval_key_value(expr, v_expr)? {
+            RuntimeTypes::Number(value) => Ok(value.to_string()),
+            RuntimeTypes::String(value) => Ok(value),
+            other => Err(CodeError::new(
+                v_expr.line,
+                v_expr.start,
+                v_expr.end,
+                format!("A {} can not be used as key", other.type_name()).as_str(),
+            )),
+        }
+    }
+
+    /// Checks whether a Liste contains a value, a Tabelle contains a key or a Text contains
+    /// another text.
+    pub fn contains(
+        &mut self,
+        expr: &Expression,
+        container: &ValueExpression,
+        part: &ValueExpression,
+    ) -> Result<bool, CodeError> {
+        match self.eval_value_expression(expr, container)? {
+            RuntimeTypes::List(items) => {
+                let value = self.eval_key_value(expr, part)?;
+                Ok(items.contains(&value))
+            }
+            RuntimeTypes::Table(table) => Ok(table.contains_key(&self.eval_key(expr, part)?)),
+            RuntimeTypes::String(text) => {
+                Ok(text.contains(&self.eval_key_value(expr, part)?.to_string()))
+            }
+            other => Err(CodeError::new(
+                container.line,
+                container.start,
+                container.end,
+                format!(
+                    "enthält needs a Liste, Tabelle or Text but found a {}",
+                    other.type_name()
+                )
+                .as_str(),
+            )),
+        }
+    }
+
+    /// Assigns a Liste or Tabelle, `type_name` is the type the value must have.
+    pub fn assign_collection(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        v_expr: &ValueExpression,
+        type_name: &str,
+    ) -> Result<(), CodeError> {
+        let value = self.eval_value_expression(expr, v_expr)?;
+        if value.type_name() != type_name {
+            return Err(CodeError::new(
+                v_expr.line,
+                v_expr.start,
+                v_expr.end,
+                format!("Expected a {} but found a {}", type_name, value.type_name()).as_str(),
+            ));
+        }
+        self.set_var(var_expr, value)?;
+        Ok(())
+    }
+
+    /// Handles `name = value` and `name[index] = value`, nested accessors like `name[a].b`
+    /// are supported. A variable keeps its type when it gets a new value.
+    pub fn assign_element(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        v_expr: &ValueExpression,
+    ) -> Result<(), CodeError> {
+        let container = self.get_var(var_expr, &var_expr.var_name)?;
+        let mut value = self.eval_value_expression(expr, v_expr)?;
+        if let (true, RuntimeTypes::Decimal(_)) = (var_expr.accessors.is_empty(), &container) {
+            value = value.widen_to(&VariableType::Decimal);
+        }
+        if var_expr.accessors.is_empty() && container.type_name() != value.type_name() {
+            return Err(CodeError::new(
+                v_expr.line,
+                v_expr.start,
+                v_expr.end,
+                format!(
+                    "{} is a {} and can not become a {}",
+                    var_expr.var_name,
+                    container.type_name(),
+                    value.type_name()
+                )
+                .as_str(),
+            ));
+        }
+        let container = self.set_element(expr, var_expr, container, &var_expr.accessors, value)?;
+        self.set_var(var_expr, container)?;
+        Ok(())
+    }
+
+    fn set_element(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        container: RuntimeTypes,
+        accessors: &[Accessor],
+        value: RuntimeTypes,
+    ) -> Result<RuntimeTypes, CodeError> {
+        let Some((accessor, rest)) = accessors.split_first() else {
+            return Ok(value);
+        };
+        match (container, accessor) {
+            (RuntimeTypes::List(mut items), Accessor::Index(index)) => {
+                let idx = self.eval_index(expr, index, items.len(), false)?;
+                let item = std::mem::replace(&mut items[idx], RuntimeTypes::Number(0));
+                items[idx] = self.set_element(expr, var_expr, item, rest, value)?;
+                Ok(RuntimeTypes::List(items))
+            }
+            (RuntimeTypes::Table(mut table), Accessor::Index(key_expr)) => {
+                let key = self.eval_key(expr, key_expr)?;
+                let item = match (table.remove(&key), rest.is_empty()) {
+                    (Some(item), _) => item,
+                    (None, true) => RuntimeTypes::Number(0),
+                    (None, false) => return Err(missing_key_error(key_expr, &key)),
+                };
+                let item = self.set_element(expr, var_expr, item, rest, value)?;
+                table.insert(key, item);
+                Ok(RuntimeTypes::Table(table))
+            }
+            (_, Accessor::Slice(..)) => Err(CodeError::new(
+                var_expr.line,
+                var_expr.start,
+                var_expr.end,
+                "Can not assign to a slice",
+            )),
+            (RuntimeTypes::Record(name, mut fields), Accessor::Field(field)) => {
+                let Some(idx) = fields.iter().position(|(name, _)| *name == field.var_name) else {
+                    let fields = self.struct_fields(&name, field)?;
+                    return Err(unknown_field_error(&name, fields, field));
+                };
+                let field_type = match self.struct_fields(&name, field)?.get(idx) {
+                    Some((_, field_type)) => field_type.clone(),
+                    None => return Err(unknown_struct_error(&name, field)),
+                };
+                let item = std::mem::replace(&mut fields[idx].1, RuntimeTypes::Number(0));
+                let item = self
+                    .set_element(expr, var_expr, item, rest, value)?
+                    .widen_to(&field_type);
+                if !item.has_type(&field_type) {
+                    return Err(CodeError::new(
+                        field.line,
+                        field.start,
+                        field.end,
+                        format!(
+                            "Field {} of {} must be a {} but got a {}",
+                            field.var_name,
+                            name,
+                            field_type.name(),
+                            item.type_name()
+                        )
+                        .as_str(),
+                    ));
+                }
+                fields[idx].1 = item;
+                Ok(RuntimeTypes::Record(name, fields))
+            }
+            (other, Accessor::Field(_)) => Err(no_fields_error(var_expr, &other)),
+            (other, _) => Err(not_indexable_error(var_expr, &other)),
+        }
+    }
+
+    pub fn list_append(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        v_expr: &ValueExpression,
+    ) -> Result<(), CodeError> {
+        let mut items = match self.get_var(var_expr, &var_expr.var_name)? {
+            RuntimeTypes::List(items) => items,
+            other => {
+                return Err(CodeError::new(
+                    var_expr.line,
+                    var_expr.start,
+                    var_expr.end,
+                    format!(
+                        "Expected a Liste but {} is a {}",
+                        var_expr.var_name,
+                        other.type_name()
+                    )
+                    .as_str(),
+                ))
+            }
+        };
+        items.push(self.eval_value_expression(expr, v_expr)?);
+        self.set_var(var_expr, RuntimeTypes::List(items))?;
+        Ok(())
+    }
+
+    /// Removes the element at an index of a Liste or the entry of a key of a Tabelle.
+    pub fn remove_element(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        index: &ValueExpression,
+    ) -> Result<(), CodeError> {
+        let container = match self.get_var(var_expr, &var_expr.var_name)? {
+            RuntimeTypes::List(mut items) => {
+                let idx = self.eval_index(expr, index, items.len(), false)?;
+                items.remove(idx);
+                RuntimeTypes::List(items)
+            }
+            RuntimeTypes::Table(mut table) => {
+                let key = self.eval_key(expr, index)?;
+                if table.remove(&key).is_none() {
+                    return Err(missing_key_error(index, &key));
+                }
+                RuntimeTypes::Table(table)
+            }
+            other => {
+                return Err(CodeError::new(
+                    var_expr.line,
+                    var_expr.start,
+                    var_expr.end,
+                    format!(
+                        "Can only remove from a Liste or Tabelle, but {} is a {}",
+                        var_expr.var_name,
+                        other.type_name()
+                    )
+                    .as_str(),
+                ))
+            }
+        };
+        self.set_var(var_expr, container)?;
+        Ok(())
+    }
+
+    /// Loops over the values of a Liste or the keys of a Tabelle. With a second loop variable
+    /// the first one gets the index or key and the second one the value.
+    pub fn for_each(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        value_var_expr: Option<&IdentifierExpression>,
+        iterable: &ValueExpression,
+        body: &[Expression],
+    ) -> Result<(), CodeError> {
+        let (entries, is_table): (Vec<(RuntimeTypes, RuntimeTypes)>, bool) =
+            match self.eval_value_expression(expr, iterable)? {
+                RuntimeTypes::List(items) => (
+                    items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(idx, item)| (RuntimeTypes::Number(idx as i64), item))
+                        .collect(),
+                    false,
+                ),
+                RuntimeTypes::Table(table) => (
+                    table
+                        .into_iter()
+                        .map(|(key, value)| (RuntimeTypes::String(key), value))
+                        .collect(),
+                    true,
+                ),
+                other => {
+                    return Err(CodeError::new(
+                        iterable.line,
+                        iterable.start,
+                        iterable.end,
+                        format!(
+                            "Can only loop over a Liste or Tabelle, not a {}",
+                            other.type_name()
+                        )
+                        .as_str(),
+                    ))
+                }
+            };
+        for (key, value) in entries {
+            match value_var_expr {
+                Some(value_var_expr) => {
+                    self.set_var(var_expr, key)?;
+                    self.set_var(value_var_expr, value)?;
+                }
+                None if is_table => self.set_var(var_expr, key)?,
+                None => self.set_var(var_expr, value)?,
+            }
+            self.run_block(body)?;
+        }
+        Ok(())
+    }
+}
+
+fn not_indexable_error(var_expr: &IdentifierExpression, value: &RuntimeTypes) -> CodeError {
+    CodeError::new(
+        var_expr.line,
+        var_expr.start,
+        var_expr.end,
+        format!(
+            "Only a Liste or Tabelle can be indexed, but {} is a {}",
+            var_expr.var_name,
+            value.type_name()
+        )
+        .as_str(),
+    )
+}
+
+fn no_fields_error(var_expr: &IdentifierExpression, value: &RuntimeTypes) -> CodeError {
+    CodeError::new(
+        var_expr.line,
+        var_expr.start,
+        var_expr.end,
+        format!(
+            "Only a Struktur has fields, but {} is a {}",
+            var_expr.var_name,
+            value.type_name()
+        )
+        .as_str(),
+    )
+}
+
+fn missing_key_error(key_expr: &ValueExpression, key: &str) -> CodeError {
+    CodeError::new(
+        key_expr.line,
+        key_expr.start,
+        key_expr.end,
+        format!("The Tabelle has no key {}", key).as_str(),
+    )
+}
@@ -0,0 +1,146 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::CodeError,
+    parser::{Expression, IdentifierExpression, ValueExpression},
+};
+
+use super::{Interpreter, RuntimeTypes};
+
+/// Which files a script may read and write.
+#[derive(Clone, Debug)]
+pub enum FileAccess {
+    All,
+    /// `--sandbox` without a directory disables file access completely
+    None,
+    /// `--sandbox=<dir>` only allows files inside the directory
+    Directory(PathBuf),
+}
+
+impl Interpreter {
+    /// Handles `Lese datei "pfad" in text` and `Lese zeilen "pfad" in liste`.
+    pub fn read_file(
+        &mut self,
+        expr: &Expression,
+        path_expr: &ValueExpression,
+        var_expr: &IdentifierExpression,
+        lines: bool,
+    ) -> Result<(), CodeError> {
+        let path = self.eval_path(expr, path_expr)?;
+        let content = fs::read_to_string(&path).map_err(|err| {
+            io_error(
+                expr,
+                format!("Could not read '{}': {}", path.display(), err),
+            )
+        })?;
+        let content = content.replace("\r", "");
+        let value = if lines {
+            RuntimeTypes::List(
+                content
+                    .lines()
+                    .map(|line| RuntimeTypes::String(line.to_owned()))
+                    .collect(),
+            )
+        } else {
+            RuntimeTypes::String(content)
+        };
+        self.set_var(var_expr, value)?;
+        Ok(())
+    }
+
+    /// Handles `Schreibe wert in "pfad"` and `Anhängen wert in "pfad"`. A Liste is written
+    /// with one value per line.
+    pub fn write_file(
+        &mut self,
+        expr: &Expression,
+        v_expr: &ValueExpression,
+        path_expr: &ValueExpression,
+        append: bool,
+    ) -> Result<(), CodeError> {
+        let text = match self.eval_value_expression(expr, v_expr)? {
+            RuntimeTypes::List(items) => items
+                .iter()
+                .map(|item| format!("{}\n", item))
+                .collect::<String>(),
+            value => value.to_string(),
+        };
+        let path = self.eval_path(expr, path_expr)?;
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|err| {
+                io_error(
+                    expr,
+                    format!("Could not write '{}': {}", path.display(), err),
+                )
+            })
+    }
+
+    fn eval_path(
+        &mut self,
+        expr: &Expression,
+        path_expr: &ValueExpression,
+    ) -> Result<PathBuf, CodeError> {
+        let path = PathBuf::from(self.eval_value_expression(expr, path_expr)?.to_string());
+        self.check_path(path, path_expr.line, path_expr.start, path_expr.end)
+    }
+
+    /// Returns the path if the script may use it, errors point at the code from `start` to
+    /// `end`.
+    pub fn check_path(
+        &self,
+        path: PathBuf,
+        line: usize,
+        start: usize,
+        end: usize,
+    ) -> Result<PathBuf, CodeError> {
+        let error = |msg: String| CodeError::new(line, start, end, &msg);
+        match &self.file_access {
+            FileAccess::All => Ok(path),
+            FileAccess::None => Err(error(
+                "Files can not be used because of --sandbox".to_owned(),
+            )),
+            FileAccess::Directory(dir) => {
+                let allowed = match (dir.canonicalize(), resolve(&path)) {
+                    (Ok(dir), Some(resolved)) => resolved.starts_with(dir),
+                    _ => false,
+                };
+                if allowed {
+                    Ok(path)
+                } else {
+                    Err(error(format!(
+                        "'{}' is outside of '{}', the only directory allowed by --sandbox",
+                        path.display(),
+                        dir.display()
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Returns the absolute path without `..` and links. The file itself does not need to
+/// exist, so it can still be created.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if let Ok(resolved) = path.canonicalize() {
+        return Some(resolved);
+    }
+    let name = path.file_name()?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize().ok()?,
+        _ => std::env::current_dir().ok()?,
+    };
+    Some(parent.join(name))
+}
+
+fn io_error(expr: &Expression, msg: String) -> CodeError {
+    CodeError::new(expr.line, expr.start, expr.end, &msg)
+}
@@ -0,0 +1,496 @@
+use std::{env, path::PathBuf};
+
+use crate::{
+    error::CodeError,
+    parser::{Expression, FunctionCall},
+};
+
+use super::{parse_decimal, parse_number, Interpreter, RuntimeTypes};
+
+/// Names of the built-in functions
+pub const FUNCTIONS: &[&str] = &[
+    "Länge",
+    "Großbuchstaben",
+    "Kleinbuchstaben",
+    "Trimmen",
+    "Teiltext",
+    "Finden",
+    "Ersetzen",
+    "Teilen",
+    "Verbinden",
+    "BeginntMit",
+    "EndetMit",
+    "Wiederholen",
+    "Umkehren",
+    "Abs",
+    "Min",
+    "Max",
+    "Wurzel",
+    "Runden",
+    "Potenz",
+    "Zufall",
+    "AlsZahl",
+    "AlsKommazahl",
+    "IstZahl",
+    "DateiExistiert",
+    "Umgebung",
+    "AlsText",
+];
+
+/// Longest Text `Wiederholen` builds, in bytes, so a huge count is an error instead of a crash
+const MAX_TEXT_LEN: usize = 1 << 30;
+
+impl Interpreter {
+    /// Evaluates a call of a built-in function like `Länge(liste)` or the construction of a
+    /// Struktur like `Person("Anna", 30)`.
+    pub fn call_function(
+        &mut self,
+        expr: &Expression,
+        call: &FunctionCall,
+    ) -> Result<RuntimeTypes, CodeError> {
+        let mut values = Vec::new();
+        for arg in &call.args {
+            values.push(self.eval_value_expression(expr, arg)?);
+        }
+        if self.structs.contains_key(&call.name.var_name) {
+            return self.construct_record(call, values);
+        }
+        let args = Args { call, values };
+        match call.name.var_name.as_str() {
+            "Länge" => {
+                args.expect_count(1, 1)?;
+                match &args.values[0] {
+                    RuntimeTypes::List(items) => Ok(RuntimeTypes::Number(items.len() as i64)),
+                    RuntimeTypes::Table(table) => Ok(RuntimeTypes::Number(table.len() as i64)),
+                    RuntimeTypes::String(text) => {
+                        Ok(RuntimeTypes::Number(text.chars().count() as i64))
+                    }
+                    other => Err(args.error(
+                        0,
+                        format!(
+                            "Länge expects a Liste, Tabelle or Text but got a {}",
+                            other.type_name()
+                        ),
+                    )),
+                }
+            }
+            "Großbuchstaben" => {
+                args.expect_count(1, 1)?;
+                Ok(RuntimeTypes::String(args.text(0)?.to_uppercase()))
+            }
+            "Kleinbuchstaben" => {
+                args.expect_count(1, 1)?;
+                Ok(RuntimeTypes::String(args.text(0)?.to_lowercase()))
+            }
+            "Trimmen" => {
+                args.expect_count(1, 1)?;
+                Ok(RuntimeTypes::String(args.text(0)?.trim().to_owned()))
+            }
+            "Teiltext" => {
+                args.expect_count(2, 3)?;
+                let chars: Vec<char> = args.text(0)?.chars().collect();
+                let start = args.number(1)?;
+                if start < 0 || start as usize > chars.len() {
+                    return Err(args.error(
+                        1,
+                        format!(
+                            "Start {} is out of range, the text has {} characters",
+                            start,
+                            chars.len()
+                        ),
+                    ));
+                }
+                let start = start as usize;
+                let length = match args.values.len() {
+                    3 => args.non_negative(2)?,
+                    _ => chars.len() - start,
+                };
+                let end = (start + length).min(chars.len());
+                Ok(RuntimeTypes::String(chars[start..end].iter().collect()))
+            }
+            "Finden" => {
+                args.expect_count(2, 2)?;
+                let text = args.text(0)?;
+                let position = text
+                    .find(args.text(1)?.as_str())
+                    .map_or(-1, |idx| text[..idx].chars().count() as i64);
+                Ok(RuntimeTypes::Number(position))
+            }
+            "Ersetzen" => {
+                args.expect_count(3, 3)?;
+                let old = args.text(1)?;
+                if old.is_empty() {
+                    return Err(args.error(1, "The text to replace can not be empty".to_owned()));
+                }
+                Ok(RuntimeTypes::String(
+                    args.text(0)?.replace(old.as_str(), args.text(2)?.as_str()),
+                ))
+            }
+            "Teilen" => {
+                args.expect_count(2, 2)?;
+                let separator = args.text(1)?;
+                if separator.is_empty() {
+                    return Err(args.error(1, "The separator can not be empty".to_owned()));
+                }
+                Ok(RuntimeTypes::List(
+                    args.text(0)?
+                        .split(separator.as_str())
+                        .map(|part| RuntimeTypes::String(part.to_owned()))
+                        .collect(),
+                ))
+            }
+            "Verbinden" => {
+                args.expect_count(1, 2)?;
+                let separator = match args.values.len() {
+                    2 => args.text(1)?,
+                    _ => String::new(),
+                };
+                let parts: Vec<String> =
+                    args.list(0)?.iter().map(|item| item.to_string()).collect();
+                Ok(RuntimeTypes::String(parts.join(&separator)))
+            }
+            "BeginntMit" => {
+                args.expect_count(2, 2)?;
+                let result = args.text(0)?.starts_with(args.text(1)?.as_str());
+                Ok(RuntimeTypes::Number(result as i64))
+            }
+            "EndetMit" => {
+                args.expect_count(2, 2)?;
+                let result = args.text(0)?.ends_with(args.text(1)?.as_str());
+                Ok(RuntimeTypes::Number(result as i64))
+            }
+            "Wiederholen" => {
+                args.expect_count(2, 2)?;
+                let count = args.non_negative(1)?;
+                let text = args.text(0)?;
+                if text
+                    .len()
+                    .checked_mul(count)
+                    .is_none_or(|len| len > MAX_TEXT_LEN)
+                {
+                    return Err(args.error(
+                        1,
+                        format!(
+                            "Wiederholen can not repeat a Text {} times, it would be too long",
+                            count
+                        ),
+                    ));
+                }
+                Ok(RuntimeTypes::String(text.repeat(count)))
+            }
+            "Umkehren" => {
+                args.expect_count(1, 1)?;
+                match &args.values[0] {
+                    RuntimeTypes::List(items) => {
+                        Ok(RuntimeTypes::List(items.iter().rev().cloned().collect()))
+                    }
+                    _ => Ok(RuntimeTypes::String(args.text(0)?.chars().rev().collect())),
+                }
+            }
+            "Abs" => {
+                args.expect_count(1, 1)?;
+                match args.values[0] {
+                    RuntimeTypes::Number(value) => value
+                        .checked_abs()
+                        .map(RuntimeTypes::Number)
+                        .ok_or_else(|| too_large_error(call, format!("Abs({})", value))),
+                    _ => Ok(RuntimeTypes::Decimal(args.decimal(0)?.abs())),
+                }
+            }
+            "Min" | "Max" => {
+                args.expect_count(1, usize::MAX)?;
+                // Either the numbers themselves or a single Liste of numbers
+                let numbers = match &args.values[..] {
+                    [RuntimeTypes::List(items)] => items.as_slice(),
+                    values => values,
+                };
+                let mut result: Option<&RuntimeTypes> = None;
+                let in_list = numbers.len() != args.values.len();
+                for (idx, number) in numbers.iter().enumerate() {
+                    let Some(value) = number.as_decimal() else {
+                        return Err(args.error(
+                            if in_list { 0 } else { idx },
+                            format!(
+                                "{} expects only Zahlen but got a {}",
+                                call.name.var_name,
+                                number.type_name()
+                            ),
+                        ));
+                    };
+                    let replace = match result.and_then(|result| result.as_decimal()) {
+                        None => true,
+                        Some(current) if call.name.var_name == "Min" => value < current,
+                        Some(current) => value > current,
+                    };
+                    if replace {
+                        result = Some(number);
+                    }
+                }
+                let Some(result) = result else {
+                    return Err(args.error(
+                        0,
+                        format!("{} of an empty Liste is not defined", call.name.var_name),
+                    ));
+                };
+                // The result is only a Zahl if all values are Zahlen
+                if numbers
+                    .iter()
+                    .all(|number| matches!(number, RuntimeTypes::Number(_)))
+                {
+                    Ok(result.clone())
+                } else {
+                    Ok(RuntimeTypes::Decimal(
+                        result.as_decimal().unwrap_or_default(),
+                    ))
+                }
+            }
+            "Wurzel" => {
+                args.expect_count(1, 1)?;
+                let value = args.decimal(0)?;
+                if value < 0.0 {
+                    return Err(args.error(
+                        0,
+                        format!(
+                            "Wurzel expects a Zahl of at least 0 but got {}",
+                            args.values[0]
+                        ),
+                    ));
+                }
+                Ok(RuntimeTypes::Decimal(value.sqrt()))
+            }
+            "Runden" => {
+                args.expect_count(1, 2)?;
+                let value = args.decimal(0)?;
+                match args.values.len() {
+                    2 => {
+                        let places = args.non_negative(1)?;
+                        let factor = 10f64.powi(places.min(i32::MAX as usize) as i32);
+                        let rounded = (value * factor).round() / factor;
+                        if value.is_finite() && !rounded.is_finite() {
+                            return Err(args.error(
+                                1,
+                                format!(
+                                    "Runden can not round {} to {} decimal places",
+                                    value, places
+                                ),
+                            ));
+                        }
+                        Ok(RuntimeTypes::Decimal(rounded))
+                    }
+                    _ => to_number(value.round())
+                        .map(RuntimeTypes::Number)
+                        .ok_or_else(|| too_large_error(call, format!("Runden({})", value))),
+                }
+            }
+            "Potenz" => {
+                args.expect_count(2, 2)?;
+                match (&args.values[0], &args.values[1]) {
+                    (RuntimeTypes::Number(base), RuntimeTypes::Number(exponent))
+                        if *exponent >= 0 =>
+                    {
+                        u32::try_from(*exponent)
+                            .ok()
+                            .and_then(|exponent| base.checked_pow(exponent))
+                            .map(RuntimeTypes::Number)
+                            .ok_or_else(|| {
+                                too_large_error(
+                                    call,
+                                    format!("{} to the power of {}", base, exponent),
+                                )
+                            })
+                    }
+                    _ => Ok(RuntimeTypes::Decimal(
+                        args.decimal(0)?.powf(args.decimal(1)?),
+                    )),
+                }
+            }
+            "Zufall" => {
+                args.expect_count(2, 2)?;
+                let (min, max) = (args.number(0)?, args.number(1)?);
+                if min > max {
+                    return Err(args.error(
+                        1,
+                        format!("Expected a Zahl of at least {} but got {}", min, max),
+                    ));
+                }
+                Ok(RuntimeTypes::Number(self.random.range(min, max)))
+            }
+            "AlsZahl" => {
+                args.expect_count(1, 1)?;
+                match &args.values[0] {
+                    RuntimeTypes::Number(value) => Ok(RuntimeTypes::Number(*value)),
+                    RuntimeTypes::Decimal(value) => to_number(value.trunc())
+                        .map(RuntimeTypes::Number)
+                        .ok_or_else(|| too_large_error(call, value.to_string())),
+                    RuntimeTypes::String(text) => match parse_number(text) {
+                        Ok(value) => Ok(RuntimeTypes::Number(value)),
+                        Err(reason) => Err(args.error(0, reason)),
+                    },
+                    _ => Err(args.type_error(0, "Zahl, Kommazahl or Text")),
+                }
+            }
+            "AlsKommazahl" => {
+                args.expect_count(1, 1)?;
+                match &args.values[0] {
+                    RuntimeTypes::String(text) => match parse_decimal(text) {
+                        Ok(value) => Ok(RuntimeTypes::Decimal(value)),
+                        Err(reason) => Err(args.error(0, reason)),
+                    },
+                    _ => Ok(RuntimeTypes::Decimal(args.decimal(0)?)),
+                }
+            }
+            "IstZahl" => {
+                args.expect_count(1, 1)?;
+                let result = match &args.values[0] {
+                    RuntimeTypes::Number(_) => true,
+                    RuntimeTypes::String(text) => parse_number(text).is_ok(),
+                    _ => false,
+                };
+                Ok(RuntimeTypes::Number(result as i64))
+            }
+            "DateiExistiert" => {
+                args.expect_count(1, 1)?;
+                let arg = &call.args[0];
+                let path =
+                    self.check_path(PathBuf::from(args.text(0)?), arg.line, arg.start, arg.end)?;
+                Ok(RuntimeTypes::Number(path.is_file() as i64))
+            }
+            "Umgebung" => {
+                args.expect_count(1, 2)?;
+                let name = args.text(0)?;
+                match (env::var(&name), args.values.len()) {
+                    (Ok(value), _) => Ok(RuntimeTypes::String(value)),
+                    (Err(_), 2) => Ok(RuntimeTypes::String(args.text(1)?)),
+                    (Err(_), _) if self.strict_env => Err(args.error(
+                        0,
+                        format!("The environment variable {} is not set (--strict)", name),
+                    )),
+                    (Err(_), _) => Ok(RuntimeTypes::String(String::new())),
+                }
+            }
+            "AlsText" => {
+                args.expect_count(1, 1)?;
+                Ok(RuntimeTypes::String(args.values[0].to_string()))
+            }
+            name => Err(CodeError::new(
+                call.name.line,
+                call.name.start,
+                call.name.end,
+                format!("Unknown function {}", name).as_str(),
+            )
+            .with_suggestion(
+                name,
+                FUNCTIONS
+                    .iter()
+                    .copied()
+                    .chain(self.structs.keys().map(String::as_str)),
+            )),
+        }
+    }
+}
+
+/// Converts a whole Kommazahl to a Zahl, `None` if it does not fit.
+fn to_number(value: f64) -> Option<i64> {
+    // i64::MIN is a power of two, so it and its negation are exact as f64
+    (value >= i64::MIN as f64 && value < -(i64::MIN as f64)).then_some(value as i64)
+}
+
+/// Error for a result that does not fit in a Zahl, e.g. `Potenz(10, 30)`.
+fn too_large_error(call: &FunctionCall, result: String) -> CodeError {
+    CodeError::new(
+        call.line,
+        call.start,
+        call.end,
+        format!("{} is too large for a Zahl", result).as_str(),
+    )
+}
+
+/// Evaluated arguments of a function call, errors point at the offending argument.
+struct Args<'a> {
+    call: &'a FunctionCall,
+    values: Vec<RuntimeTypes>,
+}
+
+impl Args<'_> {
+    fn expect_count(&self, min: usize, max: usize) -> Result<(), CodeError> {
+        let count = self.values.len();
+        if count < min || count > max {
+            let expected = if min == max {
+                min.to_string()
+            } else if max == usize::MAX {
+                format!("at least {}", min)
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(CodeError::new(
+                self.call.line,
+                self.call.start,
+                self.call.end,
+                format!(
+                    "{} expects {} argument(s) but got {}",
+                    self.call.name.var_name, expected, count
+                )
+                .as_str(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn error(&self, idx: usize, msg: String) -> CodeError {
+        let arg = &self.call.args[idx];
+        CodeError::new(arg.line, arg.start, arg.end, msg.as_str())
+    }
+
+    fn type_error(&self, idx: usize, expected: &str) -> CodeError {
+        self.error(
+            idx,
+            format!(
+                "{} expects a {} as argument {} but got a {}",
+                self.call.name.var_name,
+                expected,
+                idx + 1,
+                self.values[idx].type_name()
+            ),
+        )
+    }
+
+    fn text(&self, idx: usize) -> Result<String, CodeError> {
+        match &self.values[idx] {
+            RuntimeTypes::String(text) => Ok(text.to_owned()),
+            value @ (RuntimeTypes::Number(_) | RuntimeTypes::Decimal(_)) => Ok(value.to_string()),
+            _ => Err(self.type_error(idx, "Text")),
+        }
+    }
+
+    fn number(&self, idx: usize) -> Result<i64, CodeError> {
+        match &self.values[idx] {
+            RuntimeTypes::Number(value) => Ok(*value),
+            _ => Err(self.type_error(idx, "Zahl")),
+        }
+    }
+
+    /// Accepts a Zahl as well as a Kommazahl.
+    fn decimal(&self, idx: usize) -> Result<f64, CodeError> {
+        self.values[idx]
+            .as_decimal()
+            .ok_or_else(|| self.type_error(idx, "Zahl or Kommazahl"))
+    }
+
+    fn non_negative(&self, idx: usize) -> Result<usize, CodeError> {
+        let value = self.number(idx)?;
+        if value < 0 {
+            return Err(self.error(
+                idx,
+                format!("Expected a Zahl of at least 0 but got {}", value),
+            ));
+        }
+        Ok(value as usize)
+    }
+
+    fn list(&self, idx: usize) -> Result<&Vec<RuntimeTypes>, CodeError> {
+        match &self.values[idx] {
+            RuntimeTypes::List(items) => Ok(items),
+            _ => Err(self.type_error(idx, "Liste")),
+        }
+    }
+}
@@ -1,39 +1,231 @@
-use std::{collections::HashMap, io::stdin, num::Wrapping};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    io::{stdin, stdout, BufRead, IsTerminal, Write},
+    num::Wrapping,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
+    checker::{
+        args_declaration, constant_error, error_struct_fields, StructFields, ARGS_VARIABLE,
+        ERROR_STRUCT,
+    },
     error::CodeError,
     parser::{
-        Expression, ExpressionTypes, IdentifierExpression, NumberExpression, NumberExpressionTypes,
-        TextExpression, TextExpressionTypes,
+        CompareOperator, Expression, ExpressionTypes, IdentifierExpression, NumberExpression,
+        NumberExpressionTypes, TextExpression, TextExpressionTypes, ValueExpression,
+        ValueExpressionTypes, VariableType,
     },
 };
 
-use super::RuntimeTypes;
+use super::{
+    modules::declared_variable, parse_decimal, parse_number, random::Random, FileAccess, Modules,
+    RuntimeTypes,
+};
+
+/// Highest code a script can end with, the codes above are used by the interpreter itself
+pub const MAX_EXIT_CODE: i64 = 120;
 
 pub struct Interpreter {
-    variables: HashMap<String, RuntimeTypes>,
+    pub(super) variables: HashMap<String, RuntimeTypes>,
+    /// Where the variables declared with `Konstante` or `Lade` were declared, they can not be
+    /// changed afterwards
+    pub(super) constants: HashMap<String, IdentifierExpression>,
+    /// Fields of the declared `Struktur` types
+    pub(super) structs: HashMap<String, StructFields>,
+    /// Fields of the namespaces of files loaded with `Lade`, which can not be constructed
+    pub(super) namespaces: HashMap<String, StructFields>,
+    /// Full path of the file each `Lade` namespace was loaded from
+    pub(super) imports: HashMap<String, PathBuf>,
+    /// Source of `Zufall`, seeded with `--seed` for reproducible runs
+    pub(super) random: Random,
+    pub(super) input: Box<dyn BufRead>,
+    pub(super) output: Box<dyn Write>,
+    /// Whether a person enters the input, who can be asked again after an invalid value
+    pub(super) interactive: bool,
+    /// Which files `Lese`, `Schreibe` and `Anhängen` may use, limited by `--sandbox`
+    pub(super) file_access: FileAccess,
+    /// With `--strict` reading an environment variable that is not set is an error
+    pub(super) strict_env: bool,
+    /// Set by `Beenden`, no further instructions run once it is set
+    pub(super) exit_code: Option<i32>,
+    /// Path of the running script, `Lade` resolves paths relative to it
+    pub(super) file: PathBuf,
+    /// Files loaded with `Lade`, shared with the interpreters of the loaded files
+    pub(super) modules: Rc<RefCell<Modules>>,
 }
 impl Interpreter {
-    pub fn new() -> Self {
+    /// Creates an interpreter that reads from stdin and writes to stdout.
+    pub fn new(seed: Option<u64>) -> Self {
+        let interactive = stdin().is_terminal();
+        Interpreter::with_io(
+            seed,
+            Box::new(stdin().lock()),
+            Box::new(stdout()),
+            interactive,
+        )
+    }
+
+    /// Creates an interpreter with its own input and output, e.g. in-memory buffers for tests.
+    pub fn with_io(
+        seed: Option<u64>,
+        input: Box<dyn BufRead>,
+        output: Box<dyn Write>,
+        interactive: bool,
+    ) -> Self {
         Interpreter {
-            variables: HashMap::new(),
+            variables: HashMap::from([(ARGS_VARIABLE.to_owned(), RuntimeTypes::List(Vec::new()))]),
+            constants: HashMap::from([(ARGS_VARIABLE.to_owned(), args_declaration())]),
+            structs: HashMap::from([(ERROR_STRUCT.to_owned(), error_struct_fields())]),
+            namespaces: HashMap::new(),
+            imports: HashMap::new(),
+            random: seed.map_or_else(Random::from_time, Random::new),
+            input,
+            output,
+            interactive,
+            file_access: FileAccess::All,
+            strict_env: false,
+            exit_code: None,
+            file: PathBuf::new(),
+            modules: Rc::default(),
         }
     }
 
-    pub fn run(&mut self, parsed_expressions: Vec<Expression>) -> Result<(), CodeError> {
-        for expression in parsed_expressions {
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = file.to_owned();
+        // The script itself counts as loading, so files can not load it again
+        if let Ok(path) = file.canonicalize() {
+            self.modules.borrow_mut().loading.push(path);
+        }
+        self
+    }
+
+    /// Makes the arguments after `--` available to the script as Liste `argumente`.
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        let args = args
+            .iter()
+            .map(|arg| RuntimeTypes::String(arg.to_owned()))
+            .collect();
+        self.variables
+            .insert(ARGS_VARIABLE.to_owned(), RuntimeTypes::List(args));
+        self
+    }
+
+    pub fn with_strict_env(mut self, strict_env: bool) -> Self {
+        self.strict_env = strict_env;
+        self
+    }
+
+    pub fn with_file_access(mut self, file_access: FileAccess) -> Self {
+        self.file_access = file_access;
+        self
+    }
+
+    /// Runs the program and returns its exit code, which is 0 unless `Beenden` sets another.
+    pub fn run(&mut self, parsed_expressions: Vec<Expression>) -> Result<i32, CodeError> {
+        self.run_block(&parsed_expressions)?;
+        Ok(self.exit_code.unwrap_or(0))
+    }
+
+    pub fn run_block(&mut self, expressions: &[Expression]) -> Result<(), CodeError> {
+        for expression in expressions {
+            // After `Beenden` the enclosing blocks and loops only have to finish
+            if self.exit_code.is_some() {
+                return Ok(());
+            }
             match expression.get_expression() {
                 ExpressionTypes::TextAssignment(var_expr, value) => {
-                    self.assign_string(&expression, var_expr, value)?;
+                    self.assign_string(expression, var_expr, value)?;
                 }
                 ExpressionTypes::NumberAssignment(var_expr, value) => {
-                    self.assign_number(&expression, var_expr, value)?;
+                    self.assign_number(expression, var_expr, value)?;
+                }
+                ExpressionTypes::DecimalAssignment(var_expr, value) => {
+                    self.assign_decimal(expression, var_expr, value)?;
+                }
+                ExpressionTypes::ListAssignment(var_expr, value) => {
+                    self.assign_collection(expression, var_expr, value, "Liste")?;
+                }
+                ExpressionTypes::TableAssignment(var_expr, value) => {
+                    self.assign_collection(expression, var_expr, value, "Tabelle")?;
+                }
+                ExpressionTypes::Assignment(var_expr, value) => {
+                    self.assign_element(expression, var_expr, value)?;
                 }
-                ExpressionTypes::InputStatement(var_expr) => {
-                    self.input(var_expr, &expression)?;
+                ExpressionTypes::ConstantDeclaration(declaration) => {
+                    self.run_block(std::slice::from_ref(declaration.as_ref()))?;
+                    if let Some(var_expr) = declared_variable(declaration) {
+                        self.declare_constant(var_expr);
+                    }
                 }
-                ExpressionTypes::OutputStatement(var_expr) => {
-                    self.output(var_expr)?;
+                ExpressionTypes::StructDeclaration(definition) => {
+                    self.declare_struct(definition);
+                }
+                ExpressionTypes::RecordAssignment(type_name, var_expr, value) => {
+                    self.assign_record(expression, type_name, var_expr, value.as_ref())?;
+                }
+                ExpressionTypes::ListAppend(var_expr, value) => {
+                    self.list_append(expression, var_expr, value)?;
+                }
+                ExpressionTypes::ListRemove(var_expr, index) => {
+                    self.remove_element(expression, var_expr, index)?;
+                }
+                ExpressionTypes::InputStatement(var_expr, var_type, prompt) => {
+                    self.input(var_expr, var_type, prompt.as_ref(), expression)?;
+                }
+                ExpressionTypes::OutputStatement(values, new_line) => {
+                    self.output(expression, values, *new_line)?;
+                }
+                ExpressionTypes::ForEachStatement(var_expr, value_var_expr, iterable, body) => {
+                    self.for_each(
+                        expression,
+                        var_expr,
+                        value_var_expr.as_ref(),
+                        iterable,
+                        body,
+                    )?;
+                }
+                ExpressionTypes::TryStatement(body, var_expr, handler) => {
+                    if let Err(err) = self.run_block(body) {
+                        let error = RuntimeTypes::Record(
+                            ERROR_STRUCT.to_owned(),
+                            vec![
+                                (
+                                    "nachricht".to_owned(),
+                                    RuntimeTypes::String(err.msg().to_owned()),
+                                ),
+                                ("zeile".to_owned(), RuntimeTypes::Number(err.line() as i64)),
+                            ],
+                        );
+                        self.set_var(var_expr, error)?;
+                        self.run_block(handler)?;
+                    }
+                }
+                ExpressionTypes::ReadFile(path, var_expr, lines) => {
+                    self.read_file(expression, path, var_expr, *lines)?;
+                }
+                ExpressionTypes::WriteFile(value, path, append) => {
+                    self.write_file(expression, value, path, *append)?;
+                }
+                ExpressionTypes::Import(namespace, file) => {
+                    self.import(expression, namespace, file)?;
+                }
+                ExpressionTypes::ExitStatement(code) => {
+                    self.exit(expression, code.as_ref())?;
+                }
+                ExpressionTypes::AssertStatement(condition, message) => {
+                    self.assert(expression, condition, message.as_ref())?;
+                }
+                ExpressionTypes::ThrowStatement(value) => {
+                    let msg = self.eval_value_expression(expression, value)?.to_string();
+                    return Err(CodeError::new(
+                        expression.line,
+                        value.start,
+                        value.end,
+                        &msg,
+                    ));
                 }
             }
         }
@@ -43,44 +235,194 @@ impl Interpreter {
     pub fn input(
         &mut self,
         var_expr: &IdentifierExpression,
+        var_type: &VariableType,
+        prompt: Option<&TextExpression>,
         expr: &Expression,
     ) -> Result<(), CodeError> {
-        let mut input_string = String::new();
+        let prompt = match prompt {
+            Some(prompt) => self.eval_string_expression(expr, prompt)?,
+            None => String::new(),
+        };
+        loop {
+            self.write_output(expr, &prompt)?;
 
-        if let Ok(_) = stdin().read_line(&mut input_string) {
-            self.set_var(
-                &var_expr.var_name,
-                RuntimeTypes::String(input_string.trim_end().to_string()),
-            );
-            Ok(())
-        } else {
-            Err(CodeError::new(
-                expr.line,
-                expr.start,
-                expr.end,
-                "Error while reading input!",
-            ))
+            let mut input_string = String::new();
+            match self.input.read_line(&mut input_string) {
+                Ok(0) => {
+                    return Err(CodeError::new(
+                        expr.line,
+                        expr.start,
+                        expr.end,
+                        format!(
+                            "Input ended before a value for {} was entered!",
+                            var_expr.var_name
+                        )
+                        .as_str(),
+                    ))
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    return Err(CodeError::new(
+                        expr.line,
+                        expr.start,
+                        expr.end,
+                        "Error while reading input!",
+                    ))
+                }
+            }
+            let input_string = input_string.trim_end().to_string();
+
+            let value = match var_type {
+                VariableType::Number | VariableType::Decimal => {
+                    let parsed = match var_type {
+                        VariableType::Number => {
+                            parse_number(&input_string).map(RuntimeTypes::Number)
+                        }
+                        _ => parse_decimal(&input_string).map(RuntimeTypes::Decimal),
+                    };
+                    match parsed {
+                        Ok(value) => value,
+                        // Only a person at a terminal can correct the input, piped input fails
+                        Err(reason) if self.interactive => {
+                            self.write_output(expr, &format!("{}, please try again.\n", reason))?;
+                            continue;
+                        }
+                        Err(reason) => {
+                            return Err(CodeError::new(
+                                expr.line,
+                                expr.start,
+                                expr.end,
+                                format!("Invalid input for {}: {}", var_expr.var_name, reason)
+                                    .as_str(),
+                            ))
+                        }
+                    }
+                }
+                _ => RuntimeTypes::String(input_string),
+            };
+            self.set_var(var_expr, value)?;
+            return Ok(());
+        }
+    }
+
+    pub fn exit(
+        &mut self,
+        expr: &Expression,
+        code: Option<&NumberExpression>,
+    ) -> Result<(), CodeError> {
+        let exit_code = match code {
+            Some(n_expr) => match self.eval_number_expression(expr, n_expr)? {
+                RuntimeTypes::Number(code) if (0..=MAX_EXIT_CODE).contains(&code) => code as i32,
+                value => {
+                    return Err(CodeError::new(
+                        expr.line,
+                        n_expr.start,
+                        n_expr.end,
+                        format!(
+                            "Expected an exit code from 0 to {} but got {}",
+                            MAX_EXIT_CODE, value
+                        )
+                        .as_str(),
+                    ))
+                }
+            },
+            None => 0,
+        };
+        self.exit_code = Some(exit_code);
+        Ok(())
+    }
+
+    /// Fails if the condition of `Prüfe` is 0 or empty.
+    pub fn assert(
+        &mut self,
+        expr: &Expression,
+        condition: &ValueExpression,
+        message: Option<&TextExpression>,
+    ) -> Result<(), CodeError> {
+        let passed = match self.eval_value_expression(expr, condition)? {
+            RuntimeTypes::Number(value) => value != 0,
+            RuntimeTypes::Decimal(value) => value != 0.0,
+            RuntimeTypes::String(text) => !text.is_empty(),
+            RuntimeTypes::List(items) => !items.is_empty(),
+            RuntimeTypes::Table(table) => !table.is_empty(),
+            RuntimeTypes::Record(..) => true,
+        };
+        if passed {
+            return Ok(());
         }
+        let msg = match message {
+            Some(message) => format!(
+                "Assertion failed: {}",
+                self.eval_string_expression(expr, message)?
+            ),
+            None => "Assertion failed".to_owned(),
+        };
+        Err(CodeError::new(
+            expr.line,
+            condition.start,
+            condition.end,
+            &msg,
+        ))
     }
 
-    pub fn output(&mut self, var_expr: &IdentifierExpression) -> Result<(), CodeError> {
-        let val = self.get_var(&var_expr, &var_expr.var_name)?;
+    pub fn output(
+        &mut self,
+        expr: &Expression,
+        values: &[ValueExpression],
+        new_line: bool,
+    ) -> Result<(), CodeError> {
+        let mut texts = Vec::new();
+        for value in values {
+            texts.push(self.eval_value_expression(expr, value)?.to_string());
+        }
 
-        if let Some(val) = val.as_string() {
-            println!("{}", val);
-            Ok(())
-        } else {
-            Err(CodeError::new(
-                var_expr.line,
-                var_expr.start,
-                var_expr.end,
-                format!("Could not print variable {} as text!", var_expr.var_name).as_str(),
-            ))
+        let mut text = texts.join(" ");
+        if new_line {
+            text.push('\n');
         }
+        self.write_output(expr, &text)
     }
 
-    pub fn set_var(&mut self, var_name: &String, value: RuntimeTypes) {
-        self.variables.insert(var_name.to_owned(), value);
+    fn write_output(&mut self, expr: &Expression, text: &str) -> Result<(), CodeError> {
+        self.output
+            .write_all(text.as_bytes())
+            .and_then(|_| self.output.flush())
+            .map_err(|_| {
+                CodeError::new(
+                    expr.line,
+                    expr.start,
+                    expr.end,
+                    "Error while writing output!",
+                )
+            })
+    }
+
+    /// Sets a variable, or changes an element of it. A Konstante can only be set again by its
+    /// own declaration, which runs again in a loop.
+    pub fn set_var(
+        &mut self,
+        var_expr: &IdentifierExpression,
+        value: RuntimeTypes,
+    ) -> Result<(), CodeError> {
+        if let Some(declared) = self.constants.get(&var_expr.var_name) {
+            if declared.start != var_expr.start || declared.line != var_expr.line {
+                return Err(constant_error(var_expr, declared));
+            }
+        }
+        self.variables.insert(var_expr.var_name.to_owned(), value);
+        Ok(())
+    }
+
+    pub fn declare_constant(&mut self, var_expr: &IdentifierExpression) {
+        self.constants.insert(
+            var_expr.var_name.to_owned(),
+            IdentifierExpression::new(
+                var_expr.line,
+                var_expr.start,
+                var_expr.end,
+                var_expr.var_name.to_owned(),
+            ),
+        );
     }
 
     pub fn get_var(
@@ -96,52 +438,124 @@ impl Interpreter {
                 expr.start,
                 expr.end,
                 format!("Variable {} not set!", var_name).as_str(),
-            ))
+            )
+            .with_suggestion(var_name, self.variables.keys().map(String::as_str)))
+        }
+    }
+
+    pub fn eval_value_expression(
+        &mut self,
+        expr: &Expression,
+        v_expr: &ValueExpression,
+    ) -> Result<RuntimeTypes, CodeError> {
+        match v_expr.get_expression() {
+            ValueExpressionTypes::Identifier(var_expr) => self.eval_identifier(expr, var_expr),
+            ValueExpressionTypes::Call(call) => self.call_function(expr, call),
+            ValueExpressionTypes::Number(n_expr) => self.eval_number_expression(expr, n_expr),
+            ValueExpressionTypes::Text(t_expr) => Ok(RuntimeTypes::String(
+                self.eval_string_expression(expr, t_expr)?,
+            )),
+            ValueExpressionTypes::List(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.eval_value_expression(expr, item)?);
+                }
+                Ok(RuntimeTypes::List(values))
+            }
+            ValueExpressionTypes::Table(entries) => {
+                let mut table = BTreeMap::new();
+                for (key, value) in entries {
+                    let key = self.eval_key(expr, key)?;
+                    table.insert(key, self.eval_value_expression(expr, value)?);
+                }
+                Ok(RuntimeTypes::Table(table))
+            }
         }
     }
 
+    /// Evaluates a calculation, the result is a Zahl or, as soon as a Kommazahl is part of it,
+    /// a Kommazahl.
     pub fn eval_number_expression(
-        &self,
+        &mut self,
         expr: &Expression,
         n_expr: &NumberExpression,
-    ) -> Result<i64, CodeError> {
+    ) -> Result<RuntimeTypes, CodeError> {
         match n_expr.get_expression() {
-            NumberExpressionTypes::Value(value) => Ok(*value),
+            NumberExpressionTypes::Value(value) => Ok(RuntimeTypes::Number(*value)),
+            NumberExpressionTypes::Decimal(value) => Ok(RuntimeTypes::Decimal(*value)),
+            NumberExpressionTypes::Call(call) => match self.call_function(expr, call)? {
+                value @ (RuntimeTypes::Number(_) | RuntimeTypes::Decimal(_)) => Ok(value),
+                value => Err(CodeError::new(
+                    call.line,
+                    call.start,
+                    call.end,
+                    format!(
+                        "Expected a Zahl but {} returned a {}",
+                        call.name.var_name,
+                        value.type_name()
+                    )
+                    .as_str(),
+                )),
+            },
+            NumberExpressionTypes::Compare(operator, lhs, rhs) => {
+                let lhs_value = self.eval_value_expression(expr, lhs)?;
+                let rhs_value = self.eval_value_expression(expr, rhs)?;
+                match compare(*operator, &lhs_value, &rhs_value) {
+                    Some(result) => Ok(RuntimeTypes::Number(result as i64)),
+                    None => Err(CodeError::new(
+                        expr.line,
+                        n_expr.start,
+                        n_expr.end,
+                        format!(
+                            "Can not compare a {} with a {} by size",
+                            lhs_value.type_name(),
+                            rhs_value.type_name()
+                        )
+                        .as_str(),
+                    )),
+                }
+            }
+            NumberExpressionTypes::Contains(container, part) => Ok(RuntimeTypes::Number(
+                self.contains(expr, container, part)? as i64,
+            )),
             NumberExpressionTypes::Identifier(var_expr) => {
-                if let Some(value) = self.get_var(var_expr, &var_expr.var_name)?.as_number() {
-                    Ok(value)
-                } else {
-                    Err(CodeError::new(
+                match self.eval_identifier(expr, var_expr)? {
+                    value @ (RuntimeTypes::Number(_) | RuntimeTypes::Decimal(_)) => Ok(value),
+                    RuntimeTypes::String(text) => match parse_number(&text) {
+                        Ok(number) => Ok(RuntimeTypes::Number(number)),
+                        Err(reason) => Err(CodeError::new(
+                            expr.line,
+                            var_expr.start,
+                            var_expr.end,
+                            format!(
+                                "Could not convert the Text {} to a Zahl: {}",
+                                var_expr.var_name, reason
+                            )
+                            .as_str(),
+                        )),
+                    },
+                    value => Err(CodeError::new(
                         expr.line,
                         var_expr.start,
                         var_expr.end,
                         format!(
-                            "Could not convert Text variable {} to number!",
-                            var_expr.var_name
+                            "Expected a Zahl but {} is a {}",
+                            var_expr.var_name,
+                            value.type_name()
                         )
                         .as_str(),
-                    ))
+                    )),
                 }
             }
-            NumberExpressionTypes::Add(val1, val2) => {
-                Ok((Wrapping(self.eval_number_expression(expr, val1)?)
-                    + Wrapping(self.eval_number_expression(expr, val2)?))
-                .0)
-            }
-            NumberExpressionTypes::Sub(val1, val2) => {
-                Ok((Wrapping(self.eval_number_expression(expr, val1)?)
-                    - Wrapping(self.eval_number_expression(expr, val2)?))
-                .0)
-            }
-            NumberExpressionTypes::Mult(val1, val2) => {
-                Ok((Wrapping(self.eval_number_expression(expr, val1)?)
-                    * Wrapping(self.eval_number_expression(expr, val2)?))
-                .0)
-            }
-            NumberExpressionTypes::Div(val1, val2) => {
-                Ok((Wrapping(self.eval_number_expression(expr, val1)?)
-                    / Wrapping(self.eval_number_expression(expr, val2)?))
-                .0)
+            NumberExpressionTypes::Add(val1, val2)
+            | NumberExpressionTypes::Sub(val1, val2)
+            | NumberExpressionTypes::Mult(val1, val2)
+            | NumberExpressionTypes::Div(val1, val2) => {
+                let lhs = self.eval_number_expression(expr, val1)?;
+                let rhs = self.eval_number_expression(expr, val2)?;
+                calculate(n_expr.get_expression(), lhs, rhs).ok_or_else(|| {
+                    CodeError::new(expr.line, val2.start, val2.end, "Division by zero")
+                })
             }
         }
     }
@@ -155,22 +569,32 @@ impl Interpreter {
             TextExpressionTypes::Concat(expr1, expr2) => {
                 let eval_expr1 = self.eval_string_expression(expr, expr1)?;
                 let eval_expr2 = self.eval_string_expression(expr, expr2)?;
-                if expr1.get_expression().is_identifier()
-                    || expr2.get_expression().is_identifier()
-                    || eval_expr1.ends_with("$")
-                {
-                    Ok(format!("{}{}", eval_expr1, eval_expr2))
-                } else {
-                    Ok(format!("{} {}", eval_expr1, eval_expr2))
-                }
+                Ok(format!("{}{}", eval_expr1, eval_expr2))
             }
             TextExpressionTypes::Value(value) => Ok(value.to_owned()),
+            TextExpressionTypes::Number(n_expr) => {
+                Ok(self.eval_number_expression(expr, n_expr)?.to_string())
+            }
+            TextExpressionTypes::Formatted(inner_expr, format) => {
+                let value = match inner_expr.get_expression() {
+                    TextExpressionTypes::Identifier(var_expr) => {
+                        self.eval_identifier(expr, var_expr)?
+                    }
+                    TextExpressionTypes::Call(call) => self.call_function(expr, call)?,
+                    TextExpressionTypes::Number(n_expr) => {
+                        self.eval_number_expression(expr, n_expr)?
+                    }
+                    _ => RuntimeTypes::String(self.eval_string_expression(expr, inner_expr)?),
+                };
+                Ok(value.format(format))
+            }
+            TextExpressionTypes::Call(call) => Ok(self.call_function(expr, call)?.to_string()),
             TextExpressionTypes::Identifier(var_expr) => {
-                if let Some(string) = self.get_var(var_expr, &var_expr.var_name)?.as_string() {
+                if let Some(string) = self.eval_identifier(expr, var_expr)?.as_string() {
                     Ok(string)
                 } else {
                     Err(CodeError::new(
-                        t_expr.line,
+                        expr.line,
                         t_expr.start,
                         t_expr.end,
                         format!(
@@ -191,7 +615,26 @@ impl Interpreter {
         n_expr: &NumberExpression,
     ) -> Result<(), CodeError> {
         let value = self.eval_number_expression(expr, n_expr)?;
-        self.set_var(&var_expr.var_name, RuntimeTypes::Number(value));
+        if let RuntimeTypes::Decimal(_) = value {
+            return Err(CodeError::new(
+                expr.line,
+                n_expr.start,
+                n_expr.end,
+                "Expected a Zahl but found a Kommazahl, use Runden or AlsZahl to convert it",
+            ));
+        }
+        self.set_var(var_expr, value)?;
+        Ok(())
+    }
+
+    pub fn assign_decimal(
+        &mut self,
+        expr: &Expression,
+        var_expr: &IdentifierExpression,
+        n_expr: &NumberExpression,
+    ) -> Result<(), CodeError> {
+        let value = self.eval_number_expression(expr, n_expr)?;
+        self.set_var(var_expr, value.widen_to(&VariableType::Decimal))?;
         Ok(())
     }
 
@@ -202,7 +645,243 @@ impl Interpreter {
         s_expr: &TextExpression,
     ) -> Result<(), CodeError> {
         let value = self.eval_string_expression(expr, s_expr)?;
-        self.set_var(&var_expr.var_name, RuntimeTypes::String(value));
+        self.set_var(var_expr, RuntimeTypes::String(value))?;
         Ok(())
     }
 }
+
+/// Compares two values, numbers by value and texts alphabetically. Other values can only be
+/// compared for equality, otherwise `None` is returned.
+fn compare(operator: CompareOperator, lhs: &RuntimeTypes, rhs: &RuntimeTypes) -> Option<bool> {
+    let ordering = match (lhs, rhs) {
+        (RuntimeTypes::Number(lhs), RuntimeTypes::Number(rhs)) => Some(lhs.cmp(rhs)),
+        (RuntimeTypes::String(lhs), RuntimeTypes::String(rhs)) => Some(lhs.cmp(rhs)),
+        _ => match (lhs.as_decimal(), rhs.as_decimal()) {
+            (Some(lhs), Some(rhs)) => lhs.partial_cmp(&rhs),
+            _ => None,
+        },
+    };
+    match (operator, ordering) {
+        (CompareOperator::Equal, None) => Some(lhs == rhs),
+        (CompareOperator::NotEqual, None) => Some(lhs != rhs),
+        (_, None) => None,
+        (CompareOperator::Equal, Some(ordering)) => Some(ordering.is_eq()),
+        (CompareOperator::NotEqual, Some(ordering)) => Some(ordering.is_ne()),
+        (CompareOperator::Less, Some(ordering)) => Some(ordering.is_lt()),
+        (CompareOperator::LessEqual, Some(ordering)) => Some(ordering.is_le()),
+        (CompareOperator::Greater, Some(ordering)) => Some(ordering.is_gt()),
+        (CompareOperator::GreaterEqual, Some(ordering)) => Some(ordering.is_ge()),
+    }
+}
+
+/// Applies the operation of `operation`, returns `None` on a division by zero.
+fn calculate(
+    operation: &NumberExpressionTypes,
+    lhs: RuntimeTypes,
+    rhs: RuntimeTypes,
+) -> Option<RuntimeTypes> {
+    match (lhs, rhs) {
+        (RuntimeTypes::Number(lhs), RuntimeTypes::Number(rhs)) => {
+            let (lhs, rhs) = (Wrapping(lhs), Wrapping(rhs));
+            Some(RuntimeTypes::Number(
+                match operation {
+                    NumberExpressionTypes::Add(..) => lhs + rhs,
+                    NumberExpressionTypes::Sub(..) => lhs - rhs,
+                    NumberExpressionTypes::Mult(..) => lhs * rhs,
+                    _ if rhs.0 == 0 => return None,
+                    _ => lhs / rhs,
+                }
+                .0,
+            ))
+        }
+        (lhs, rhs) => {
+            let lhs = lhs.as_decimal().unwrap_or_default();
+            let rhs = rhs.as_decimal().unwrap_or_default();
+            Some(RuntimeTypes::Decimal(match operation {
+                NumberExpressionTypes::Add(..) => lhs + rhs,
+                NumberExpressionTypes::Sub(..) => lhs - rhs,
+                NumberExpressionTypes::Mult(..) => lhs * rhs,
+                _ if rhs == 0.0 => return None,
+                _ => lhs / rhs,
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Cursor},
+        path::Path,
+    };
+
+    use super::Interpreter;
+    use crate::{checker::TypeChecker, lexer::Lexer, parser::Parser};
+
+    /// Runs a script file like `red` does, with the type checker.
+    fn run_file(path: &str) -> Result<i32, String> {
+        let mut source = fs::read_to_string(path).unwrap();
+        source.push('\n');
+        let tokens = Lexer::new(&source).lex().unwrap();
+        let parsed = Parser::new(tokens).parse().unwrap();
+        TypeChecker::new()
+            .check(&parsed)
+            .map_err(|err| err.msg().to_owned())?;
+        Interpreter::with_io(
+            Some(1),
+            Box::new(Cursor::new(Vec::new())),
+            Box::new(io::sink()),
+            false,
+        )
+        .with_file(Path::new(path))
+        .run(parsed)
+        .map_err(|err| err.msg().to_owned())
+    }
+
+    /// Runs a script like `red` does, with the type checker.
+    fn run(source: &str) -> Result<i32, String> {
+        let tokens = Lexer::new(source).lex().unwrap();
+        let parsed = Parser::new(tokens).parse().unwrap();
+        TypeChecker::new()
+            .check(&parsed)
+            .map_err(|err| err.msg().to_owned())?;
+        run_unchecked(source)
+    }
+
+    /// Runs a script without the type checker, so only the interpreter can stop it.
+    fn run_unchecked(source: &str) -> Result<i32, String> {
+        let tokens = Lexer::new(source).lex().unwrap();
+        let parsed = Parser::new(tokens).parse().unwrap();
+        Interpreter::with_io(
+            Some(1),
+            Box::new(Cursor::new(Vec::new())),
+            Box::new(io::sink()),
+            false,
+        )
+        .run(parsed)
+        .map_err(|err| err.msg().to_owned())
+    }
+
+    #[test]
+    fn constants_can_not_be_changed_at_runtime() {
+        let changes = [
+            "l[0] = 2",
+            "Hinzufügen l 2",
+            "Entfernen l 0",
+            "Liste l = [2]",
+            "Für jedes l in [[2]]\nEnde",
+        ];
+        for change in changes {
+            let source = format!("Konstante Liste l = [1]\n{}\n", change);
+            assert_eq!(
+                run_unchecked(&source),
+                Err("l is a Konstante and can not be changed".to_owned()),
+                "{}",
+                change
+            );
+        }
+    }
+
+    #[test]
+    fn constant_declarations_run_again_in_loops() {
+        let source = "Für jedes x in [1, 2]\n    Konstante Zahl y = x\nEnde\n";
+        assert_eq!(run_unchecked(source), Ok(0));
+    }
+
+    #[test]
+    fn loading_a_file_twice_runs_it_once() {
+        assert_eq!(run_file("tests/lade/zweimal.red"), Ok(0));
+    }
+
+    #[test]
+    fn bare_words_are_table_keys() {
+        let source = "Tabelle preise = {apfel: 120}
+preise[kiwi] = 45
+Text t = Ein Apfel kostet $preise[apfel]$ Cent
+Prüfe t == \"Ein Apfel kostet 120 Cent\"
+Prüfe preise enthält kiwi
+Text sorte = \"apfel\"
+Prüfe preise[sorte] == 120
+";
+        assert_eq!(run(source), Ok(0));
+    }
+
+    #[test]
+    fn results_outside_of_a_zahl_are_errors() {
+        let cases = [
+            (
+                "Kommazahl k = Runden(2.5, 400)",
+                "Runden can not round 2.5 to 400 decimal places",
+            ),
+            (
+                "Zahl z = Runden(1000000000000000000000.0)",
+                "Runden(1000000000000000000000) is too large for a Zahl",
+            ),
+            (
+                "Zahl z = AlsZahl(-1000000000000000000000.0)",
+                "-1000000000000000000000 is too large for a Zahl",
+            ),
+            (
+                "Zahl z = Abs(-9223372036854775807 - 1)",
+                "Abs(-9223372036854775808) is too large for a Zahl",
+            ),
+            (
+                "Zahl z = Max()",
+                "Max expects at least 1 argument(s) but got 0",
+            ),
+        ];
+        for (source, msg) in cases {
+            assert_eq!(
+                run_unchecked(&format!("{}\n", source)),
+                Err(msg.to_owned()),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn list_indices_outside_of_the_list_are_errors() {
+        let cases = [
+            (
+                "Zahl x = l[2]",
+                "Index 2 is out of range, the Liste has 2 elements",
+            ),
+            (
+                "Zahl x = l[0 - 1]",
+                "Index -1 is out of range, the Liste has 2 elements",
+            ),
+            (
+                "l[2] = 3",
+                "Index 2 is out of range, the Liste has 2 elements",
+            ),
+            (
+                "Entfernen l 2",
+                "Index 2 is out of range, the Liste has 2 elements",
+            ),
+            (
+                "Liste t = l[3:]",
+                "Index 3 is out of range, the Liste has 2 elements",
+            ),
+            (
+                "Liste t = l[2:1]",
+                "Slice start 2 is greater than its end 1",
+            ),
+            (
+                "Zahl x = l[\"a\"]",
+                "Expected a Zahl as index but 'a' is not a Zahl",
+            ),
+            (
+                "Zahl x = l[0][0]",
+                "Only a Liste or Tabelle can be indexed, but l is a Zahl",
+            ),
+        ];
+        for (source, msg) in cases {
+            let source = format!("Liste l = [1, 2]\n{}\n", source);
+            assert_eq!(run_unchecked(&source), Err(msg.to_owned()), "{}", source);
+        }
+        let source = "Liste l = [1, 2]\nPrüfe l[2:] == []\nPrüfe l[:2] == l\nPrüfe l[1] == 2\n";
+        assert_eq!(run_unchecked(source), Ok(0));
+    }
+}
@@ -3,3 +3,18 @@ pub use interpreter::*;
 
 mod types;
 pub use types::*;
+
+mod functions;
+pub use functions::FUNCTIONS;
+
+mod collections;
+
+mod records;
+
+mod random;
+
+mod files;
+pub use files::*;
+
+mod modules;
+pub use modules::*;
@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    checker::{namespace_collision_error, StructFields, TypeChecker, ARGS_VARIABLE},
+    error::{CodeError, Stage},
+    lexer::Lexer,
+    parser::{Expression, ExpressionTypes, IdentifierExpression, Parser},
+};
+
+use super::{Interpreter, RuntimeTypes};
+
+/// Files loaded with `Lade`, every file is only run once.
+#[derive(Default)]
+pub struct Modules {
+    /// Loaded files by their full path
+    loaded: HashMap<PathBuf, Module>,
+    /// Files that are loading right now, innermost last
+    pub(super) loading: Vec<PathBuf>,
+}
+
+/// What a loaded file shares with the files that load it.
+#[derive(Clone)]
+struct Module {
+    /// Values of the `Konstante` declarations in the order they were declared
+    constants: Vec<(String, RuntimeTypes)>,
+    /// `Struktur` types the constants can use
+    structs: HashMap<String, StructFields>,
+}
+
+impl Interpreter {
+    /// Handles `Lade "bibliothek.red"`, the constants of the file become the fields of
+    /// `bibliothek`.
+    pub fn import(
+        &mut self,
+        expr: &Expression,
+        namespace: &IdentifierExpression,
+        file: &str,
+    ) -> Result<(), CodeError> {
+        let relative_path = self.file.parent().unwrap_or(&PathBuf::new()).join(file);
+        let relative_path = self.check_path(
+            relative_path,
+            namespace.line,
+            namespace.start,
+            namespace.end,
+        )?;
+        let error = |msg: String| CodeError::new(expr.line, expr.start, expr.end, &msg);
+        let path = relative_path.canonicalize().map_err(|err| {
+            error(format!(
+                "Could not load '{}': {}",
+                relative_path.display(),
+                err
+            ))
+        })?;
+        // Loading the same file again changes nothing, it is only run once
+        if self.imports.get(&namespace.var_name) == Some(&path) {
+            return Ok(());
+        }
+
+        let cached = self.modules.borrow().loaded.get(&path).cloned();
+        let module = match cached {
+            Some(module) => module,
+            None => {
+                if let Some(chain) = self.loading_chain(&path) {
+                    return Err(error(format!(
+                        "Files load each other: {}",
+                        chain.join(" -> ")
+                    )));
+                }
+                let source = fs::read_to_string(&path).map_err(|err| {
+                    error(format!(
+                        "Could not load '{}': {}",
+                        relative_path.display(),
+                        err
+                    ))
+                })?;
+                let mut source = source.replace("\r", "");
+                source.push('\n');
+
+                self.modules.borrow_mut().loading.push(path.clone());
+                let module = self.run_module(&relative_path, &source);
+                self.modules.borrow_mut().loading.pop();
+                let module = module
+                    .map_err(|err| err.in_file(&relative_path.display().to_string(), &source))?;
+                self.modules
+                    .borrow_mut()
+                    .loaded
+                    .insert(path.clone(), module.clone());
+                module
+            }
+        };
+
+        for (name, fields) in module.structs {
+            match self.structs.get(&name) {
+                Some(existing) if *existing != fields => {
+                    return Err(error(format!(
+                        "The loaded file declares a different Struktur {} than this file",
+                        name
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    self.structs.insert(name, fields);
+                }
+            }
+        }
+        // The namespace is a record with one field per constant
+        let name = &namespace.var_name;
+        if self.structs.contains_key(name) {
+            return Err(namespace_collision_error(namespace));
+        }
+        let fields = module
+            .constants
+            .iter()
+            .map(|(constant, value)| (constant.to_owned(), value.variable_type()))
+            .collect();
+        self.namespaces.insert(name.to_owned(), fields);
+        self.set_var(
+            namespace,
+            RuntimeTypes::Record(name.to_owned(), module.constants),
+        )?;
+        self.declare_constant(namespace);
+        self.imports.insert(name.to_owned(), path);
+        Ok(())
+    }
+
+    /// Returns the files from `path` to the file that is loading it if they load each other.
+    fn loading_chain(&self, path: &PathBuf) -> Option<Vec<String>> {
+        let modules = self.modules.borrow();
+        let idx = modules.loading.iter().position(|loading| loading == path)?;
+        Some(
+            modules.loading[idx..]
+                .iter()
+                .chain([path])
+                .map(|path| file_name(path))
+                .collect(),
+        )
+    }
+
+    /// Runs a loaded file in its own interpreter, which uses the same input and output.
+    fn run_module(&mut self, path: &PathBuf, source: &str) -> Result<Module, CodeError> {
+        let tokens = Lexer::new(source)
+            .lex()
+            .map_err(|err| err.in_stage(Stage::Lexer))?;
+        let parsed = Parser::new(tokens)
+            .parse()
+            .map_err(|err| err.in_stage(Stage::Parser))?;
+        TypeChecker::new()
+            .check(&parsed)
+            .map_err(|err| err.in_stage(Stage::TypeCheck))?;
+
+        let input: Box<dyn BufRead> = Box::new(io::empty());
+        let output: Box<dyn Write> = Box::new(io::sink());
+        let mut module = Interpreter::with_io(
+            Some(self.random.next_u64()),
+            mem::replace(&mut self.input, input),
+            mem::replace(&mut self.output, output),
+            self.interactive,
+        )
+        .with_file_access(self.file_access.clone())
+        .with_strict_env(self.strict_env);
+        module.file = path.to_owned();
+        module.modules = self.modules.clone();
+        // Loaded files see the same arguments as the script
+        if let Some(args) = self.variables.get(ARGS_VARIABLE) {
+            module
+                .variables
+                .insert(ARGS_VARIABLE.to_owned(), args.clone());
+        }
+
+        let names = constant_names(&parsed);
+        let result = module.run(parsed);
+        self.input = module.input;
+        self.output = module.output;
+        // `Beenden` in a loaded file ends the whole program
+        if module.exit_code.is_some() {
+            self.exit_code = module.exit_code;
+        }
+        result?;
+
+        let constants = names
+            .into_iter()
+            .filter_map(|name| {
+                let value = module.variables.remove(&name)?;
+                Some((name, value))
+            })
+            .collect();
+        Ok(Module {
+            constants,
+            structs: module.structs,
+        })
+    }
+}
+
+/// Names of the constants declared at the top level of a file.
+fn constant_names(expressions: &[Expression]) -> Vec<String> {
+    expressions
+        .iter()
+        .filter_map(|expression| match expression.get_expression() {
+            ExpressionTypes::ConstantDeclaration(declaration) => {
+                declared_variable(declaration).map(|var_expr| var_expr.var_name.to_owned())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The variable a declaration like `Zahl x = 1` declares.
+pub fn declared_variable(declaration: &Expression) -> Option<&IdentifierExpression> {
+    match declaration.get_expression() {
+        ExpressionTypes::TextAssignment(var_expr, _)
+        | ExpressionTypes::NumberAssignment(var_expr, _)
+        | ExpressionTypes::DecimalAssignment(var_expr, _)
+        | ExpressionTypes::ListAssignment(var_expr, _)
+        | ExpressionTypes::TableAssignment(var_expr, _)
+        | ExpressionTypes::RecordAssignment(_, var_expr, _) => Some(var_expr),
+        _ => None,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    checker::{unknown_struct_error, StructFields},
+    error::CodeError,
+    parser::{
+        Expression, FunctionCall, IdentifierExpression, StructDefinition, ValueExpression,
+        VariableType,
+    },
+};
+
+use super::{Interpreter, RuntimeTypes};
+
+impl Interpreter {
+    pub fn declare_struct(&mut self, definition: &StructDefinition) {
+        let fields = definition
+            .fields
+            .iter()
+            .map(|field| (field.name.var_name.to_owned(), field.field_type.clone()))
+            .collect();
+        self.structs
+            .insert(definition.name.var_name.to_owned(), fields);
+    }
+
+    /// Handles `Person name = value`, without a value all fields are empty.
+    pub fn assign_record(
+        &mut self,
+        expr: &Expression,
+        type_name: &IdentifierExpression,
+        var_expr: &IdentifierExpression,
+        v_expr: Option<&ValueExpression>,
+    ) -> Result<(), CodeError> {
+        let record_type = VariableType::Record(type_name.var_name.to_owned());
+        let value = match v_expr {
+            Some(v_expr) => {
+                let value = self.eval_value_expression(expr, v_expr)?;
+                if !value.has_type(&record_type) {
+                    return Err(CodeError::new(
+                        v_expr.line,
+                        v_expr.start,
+                        v_expr.end,
+                        format!(
+                            "Expected a {} but found a {}",
+                            type_name.var_name,
+                            value.type_name()
+                        )
+                        .as_str(),
+                    ));
+                }
+                value
+            }
+            None => self.default_value(&record_type, type_name)?,
+        };
+        self.set_var(var_expr, value)?;
+        Ok(())
+    }
+
+    /// Returns the fields of a `Struktur` or a `Lade` namespace, `at` is where the error for an
+    /// unknown one is shown.
+    pub fn struct_fields(
+        &self,
+        name: &str,
+        at: &IdentifierExpression,
+    ) -> Result<&StructFields, CodeError> {
+        self.structs
+            .get(name)
+            .or_else(|| self.namespaces.get(name))
+            .ok_or_else(|| unknown_struct_error(name, at))
+    }
+
+    /// Returns the empty value of a type, which is also used for fields that were not set.
+    pub fn default_value(
+        &self,
+        var_type: &VariableType,
+        at: &IdentifierExpression,
+    ) -> Result<RuntimeTypes, CodeError> {
+        Ok(match var_type {
+            VariableType::Text => RuntimeTypes::String(String::new()),
+            VariableType::Number => RuntimeTypes::Number(0),
+            VariableType::Decimal => RuntimeTypes::Decimal(0.0),
+            VariableType::List => RuntimeTypes::List(Vec::new()),
+            VariableType::Table => RuntimeTypes::Table(BTreeMap::new()),
+            VariableType::Record(name) => {
+                let mut fields = Vec::new();
+                for (field, field_type) in self.struct_fields(name, at)? {
+                    fields.push((field.to_owned(), self.default_value(field_type, at)?));
+                }
+                RuntimeTypes::Record(name.to_owned(), fields)
+            }
+        })
+    }
+
+    /// Creates a record from a call like `Person("Anna", 30)` with one value per field.
+    pub fn construct_record(
+        &self,
+        call: &FunctionCall,
+        args: Vec<RuntimeTypes>,
+    ) -> Result<RuntimeTypes, CodeError> {
+        let name = &call.name.var_name;
+        let fields = self.struct_fields(name, &call.name)?;
+        if args.len() != fields.len() {
+            return Err(CodeError::new(
+                call.line,
+                call.start,
+                call.end,
+                format!(
+                    "{} has {} fields but got {} values",
+                    name,
+                    fields.len(),
+                    args.len()
+                )
+                .as_str(),
+            ));
+        }
+        let mut values = Vec::new();
+        for (((field, field_type), value), arg) in fields.iter().zip(args).zip(&call.args) {
+            let value = value.widen_to(field_type);
+            if !value.has_type(field_type) {
+                return Err(CodeError::new(
+                    arg.line,
+                    arg.start,
+                    arg.end,
+                    format!(
+                        "Field {} of {} must be a {} but got a {}",
+                        field,
+                        name,
+                        field_type.name(),
+                        value.type_name()
+                    )
+                    .as_str(),
+                ));
+            }
+            values.push((field.to_owned(), value));
+        }
+        Ok(RuntimeTypes::Record(name.to_owned(), values))
+    }
+}
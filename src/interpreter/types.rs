@@ -1,26 +1,190 @@
-#[derive(Clone)]
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::parser::{FormatAlign, FormatSpec, VariableType};
+
+#[derive(Clone, PartialEq)]
 pub enum RuntimeTypes {
     Number(i64),
+    /// A Kommazahl
+    Decimal(f64),
     String(String),
+    List(Vec<RuntimeTypes>),
+    /// Values by text keys, kept in the order of the keys
+    Table(BTreeMap<String, RuntimeTypes>),
+    /// Name of the `Struktur` and its fields in the declared order
+    Record(String, Vec<(String, RuntimeTypes)>),
 }
 
-impl RuntimeTypes {
-    pub fn as_number(&self) -> Option<i64> {
+impl Display for RuntimeTypes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            RuntimeTypes::Number(value) => Some(*value),
-            RuntimeTypes::String(string) => {
-                if let Ok(value) = string.parse::<i64>() {
-                    Some(value)
-                } else {
-                    None
+            RuntimeTypes::Number(val) => write!(f, "{}", val),
+            // Debug keeps the fraction of whole numbers, so 3.0 is not printed as 3
+            RuntimeTypes::Decimal(val) => write!(f, "{:?}", val),
+            RuntimeTypes::String(val) => write!(f, "{}", val),
+            RuntimeTypes::List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            RuntimeTypes::Table(table) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in table.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
                 }
+                write!(f, "}}")
             }
+            RuntimeTypes::Record(name, fields) => {
+                write!(f, "{} {{", name)?;
+                for (idx, (field, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", field, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl RuntimeTypes {
+    /// Returns a Zahl or Kommazahl as Kommazahl.
+    pub fn as_decimal(&self) -> Option<f64> {
+        match self {
+            RuntimeTypes::Number(value) => Some(*value as f64),
+            RuntimeTypes::Decimal(value) => Some(*value),
+            _ => None,
         }
     }
     pub fn as_string(&self) -> Option<String> {
         match self {
             RuntimeTypes::Number(val) => Some(val.to_string()),
             RuntimeTypes::String(val) => Some(val.to_owned()),
+            RuntimeTypes::Decimal(_) => Some(self.to_string()),
+            RuntimeTypes::List(_) | RuntimeTypes::Table(_) | RuntimeTypes::Record(..) => {
+                Some(self.to_string())
+            }
+        }
+    }
+    /// Name of the type as written in red code, used in error messages.
+    pub fn type_name(&self) -> &str {
+        match self {
+            RuntimeTypes::Number(_) => "Zahl",
+            RuntimeTypes::Decimal(_) => "Kommazahl",
+            RuntimeTypes::String(_) => "Text",
+            RuntimeTypes::List(_) => "Liste",
+            RuntimeTypes::Table(_) => "Tabelle",
+            RuntimeTypes::Record(name, _) => name,
+        }
+    }
+    pub fn variable_type(&self) -> VariableType {
+        match self {
+            RuntimeTypes::Number(_) => VariableType::Number,
+            RuntimeTypes::Decimal(_) => VariableType::Decimal,
+            RuntimeTypes::String(_) => VariableType::Text,
+            RuntimeTypes::List(_) => VariableType::List,
+            RuntimeTypes::Table(_) => VariableType::Table,
+            RuntimeTypes::Record(name, _) => VariableType::Record(name.to_owned()),
+        }
+    }
+    pub fn has_type(&self, var_type: &VariableType) -> bool {
+        self.type_name() == var_type.name()
+    }
+    /// Returns the value as Kommazahl if it is a Zahl and a Kommazahl is expected, since
+    /// every Zahl can be used as Kommazahl.
+    pub fn widen_to(self, var_type: &VariableType) -> Self {
+        match (self, var_type) {
+            (RuntimeTypes::Number(val), VariableType::Decimal) => RuntimeTypes::Decimal(val as f64),
+            (value, _) => value,
+        }
+    }
+    pub fn format(&self, spec: &FormatSpec) -> String {
+        let (text, is_number) = match self {
+            RuntimeTypes::Number(val) => match spec.precision {
+                Some(precision) => (format!("{:.*}", precision, *val as f64), true),
+                None => (val.to_string(), true),
+            },
+            RuntimeTypes::Decimal(val) => match spec.precision {
+                Some(precision) => (format!("{:.*}", precision, val), true),
+                None => (self.to_string(), true),
+            },
+            RuntimeTypes::String(val) => match spec.precision {
+                Some(precision) => (val.chars().take(precision).collect(), false),
+                None => (val.to_owned(), false),
+            },
+            RuntimeTypes::List(_) | RuntimeTypes::Table(_) | RuntimeTypes::Record(..) => {
+                (self.to_string(), false)
+            }
+        };
+        let padding = spec.width.saturating_sub(text.chars().count());
+        if padding == 0 {
+            return text;
+        }
+        if spec.zero_pad && is_number && spec.align.is_none() {
+            return match text.strip_prefix('-') {
+                Some(digits) => format!("-{}{}", "0".repeat(padding), digits),
+                None => format!("{}{}", "0".repeat(padding), text),
+            };
         }
+        let fill = |count: usize| spec.fill.to_string().repeat(count);
+        let default_align = if is_number {
+            FormatAlign::Right
+        } else {
+            FormatAlign::Left
+        };
+        match spec.align.unwrap_or(default_align) {
+            FormatAlign::Left => format!("{}{}", text, fill(padding)),
+            FormatAlign::Right => format!("{}{}", fill(padding), text),
+            FormatAlign::Center => format!(
+                "{}{}{}",
+                fill(padding / 2),
+                text,
+                fill(padding - padding / 2)
+            ),
+        }
+    }
+}
+
+/// Parses a text like `" 42 "` as Zahl, the error explains why the text is not a Zahl.
+pub fn parse_number(text: &str) -> Result<i64, String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(format!("'{}' is empty and not a Zahl", text));
+    }
+    trimmed.parse::<i64>().map_err(|_| {
+        let is_digits = trimmed
+            .strip_prefix(['-', '+'])
+            .unwrap_or(trimmed)
+            .chars()
+            .all(|c| c.is_ascii_digit());
+        if is_digits {
+            format!("'{}' is too large for a Zahl", text)
+        } else if parse_decimal(text).is_ok() {
+            format!("'{}' is a Kommazahl and not a Zahl", text)
+        } else {
+            format!("'{}' is not a Zahl", text)
+        }
+    })
+}
+
+/// Parses a text like `"2.5"` or `"2,5"` as Kommazahl.
+pub fn parse_decimal(text: &str) -> Result<f64, String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(format!("'{}' is empty and not a Kommazahl", text));
+    }
+    // A decimal comma is accepted as well, as usual in German
+    match trimmed.replace(',', ".").parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(format!("'{}' is not a Kommazahl", text)),
     }
 }
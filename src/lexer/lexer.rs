@@ -1,15 +1,44 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashSet};
 
 use crate::error::CodeError;
 
 use super::{Token, TokenType};
 
+/// Words that start an instruction, `Für` is followed by `jedes`.
+pub const KEYWORDS: &[&str] = &[
+    "Text",
+    "Zahl",
+    "Kommazahl",
+    "Liste",
+    "Tabelle",
+    "Struktur",
+    "Konstante",
+    "Output",
+    "OutputOhneZeilenumbruch",
+    "Input",
+    "Hinzufügen",
+    "Entfernen",
+    "Für",
+    "Versuche",
+    "Fehler",
+    "Wirf",
+    "Prüfe",
+    "Lese",
+    "Schreibe",
+    "Anhängen",
+    "Beenden",
+    "Lade",
+    "Ende",
+];
+
 pub struct Lexer {
     input: String,
     current: usize,
     start: usize,
     line: usize,
     tokens: Vec<Token>,
+    /// Names of the declared `Struktur` types, lines starting with one declare a variable
+    struct_names: HashSet<String>,
 }
 
 impl Lexer {
@@ -20,6 +49,7 @@ impl Lexer {
             start: 0,
             line: 1,
             tokens: Vec::new(),
+            struct_names: HashSet::new(),
         }
     }
 
@@ -49,14 +79,16 @@ impl Lexer {
                         self.start = self.current;
                         Ok(())
                     }
-                    _ => {
-                        return Err(CodeError::new(
-                            self.line,
-                            self.start,
-                            self.current,
-                            "Unknown token at this position!",
-                        ))
+                    '/' if self.is_next("/") => {
+                        self.current -= 1;
+                        self.comment_token()
                     }
+                    _ => Err(CodeError::new(
+                        self.line,
+                        self.start,
+                        self.current,
+                        "Unknown token at this position!",
+                    )),
                 },
             }
         } else {
@@ -67,6 +99,14 @@ impl Lexer {
 
     fn identifier_token(&mut self) -> Result<(), CodeError> {
         let mut ch = self.advance("Expected identifier name")?;
+        if !(ch.is_alphanumeric() || ch == '_') {
+            return Err(CodeError::new(
+                self.line,
+                self.start,
+                self.current,
+                "Expected identifier name",
+            ));
+        }
         while (ch.is_alphanumeric() || ch == '_') && !self.is_at_end() {
             ch = self.advance("Unexpected end of identifier")?;
         }
@@ -76,119 +116,386 @@ impl Lexer {
     }
 
     fn string_token(&mut self) -> Result<(), CodeError> {
-        self.skip_spaces()?;
+        self.skip_line_spaces()?;
+        if self.is_next("\"\"\"") {
+            self.multiline_string_token()
+        } else if !self.is_at_end() && self.get_char_at_current("Unexpected end of string")? == '"'
+        {
+            self.expression_token()
+        } else {
+            self.text_fragments('\n')
+        }
+    }
+
+    /// Lexes a text between a `"""` line and a closing `"""` line. The indentation all
+    /// non-blank lines have in common is removed.
+    fn multiline_string_token(&mut self) -> Result<(), CodeError> {
+        let (open_line, open_start) = (self.line, self.current);
+        self.current += 3;
+        self.skip_line_spaces()?;
+        if !self.match_next('\n')? {
+            return Err(CodeError::new(
+                self.line,
+                open_start,
+                self.current,
+                "Expected new line after \"\"\"",
+            ));
+        }
+        self.line += 1;
+
+        let remaining: String = self.input.chars().skip(self.current).collect();
+        let lines: Vec<&str> = remaining
+            .split('\n')
+            .take_while(|line| line.trim() != "\"\"\"")
+            .collect();
+        if lines.len() == remaining.split('\n').count() {
+            return Err(CodeError::new(
+                open_line,
+                open_start,
+                open_start + 3,
+                "Missing closing \"\"\" for this text",
+            ));
+        }
+        let indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.chars().take_while(|ch| ch.is_whitespace()).count())
+            .min()
+            .unwrap_or(0);
+
+        self.start = self.current;
+        if lines.is_empty() {
+            self.add_token_with_value(TokenType::VALUE, String::new());
+        }
+        for (idx, line) in lines.iter().enumerate() {
+            self.current += line
+                .chars()
+                .take(indent)
+                .take_while(|ch| ch.is_whitespace())
+                .count();
+            self.start = self.current;
+            self.text_fragments('\n')?;
+            self.current += 1;
+            if idx + 1 < lines.len() {
+                self.add_token_with_value(TokenType::VALUE, "\n".to_owned());
+            }
+            self.line += 1;
+        }
+
+        self.skip_line_spaces()?;
+        self.current += 3;
+        self.start = self.current;
+        Ok(())
+    }
+
+    fn quoted_token(&mut self) -> Result<(), CodeError> {
+        self.add_token(TokenType::QUOTE);
+        self.text_fragments('"')?;
+        if !self.match_next('"')? {
+            return Err(CodeError::new(
+                self.line,
+                self.start,
+                self.current,
+                "Expected closing \" for text",
+            ));
+        }
+        self.add_token(TokenType::QUOTE);
+        Ok(())
+    }
+
+    /// Lexes text up to (but not including) `terminator` into VALUE tokens, with
+    /// IDENTIFIER tokens for every `$name$` in between. Escape sequences are resolved here,
+    /// so the value of a VALUE token can differ from its source slice.
+    fn text_fragments(&mut self, terminator: char) -> Result<(), CodeError> {
+        let mut fragment = String::new();
         while !self.is_at_end() {
-            let ch = self.advance("Unexpected end of string")?;
+            let ch = self.get_char_at_current("Unexpected end of string")?;
+            if ch == terminator {
+                break;
+            } else if ch == '\n' {
+                return Err(CodeError::new(
+                    self.line,
+                    self.start,
+                    self.current,
+                    "Expected closing \" for text",
+                ));
+            }
+            self.current += 1;
             match ch {
+                '\\' => fragment.push(self.escape_sequence()?),
                 '$' => {
                     if self.match_next('$')? {
-                        self.current -= 1;
-                        self.add_token(TokenType::VALUE);
-                        self.start = self.current + 1;
-                        self.current = self.start;
+                        fragment.push('$');
                         continue;
-                    } else {
-                        self.current -= 1;
-                        self.add_token(TokenType::VALUE);
-                        self.start = self.current + 1;
-                        self.current = self.start;
-
-                        self.identifier_token()?;
-                        if !self.match_next('$')? {
-                            return Err(CodeError::new(
-                                self.line,
-                                self.start,
-                                self.current,
-                                "Expected closing $ for variable",
-                            ));
-                        } else if self.match_next('$')? {
-                            return Err(CodeError::new(
-                                self.line,
-                                self.start,
-                                self.current,
-                                "Found $$ but expected $ because of present variable",
-                            ));
-                        } else {
-                            self.start = self.current;
-                            continue;
-                        }
                     }
-                }
-                '\n' => {
                     self.current -= 1;
-                    break;
+                    self.add_token_with_value(TokenType::VALUE, fragment);
+                    fragment = String::new();
+                    self.current += 1;
+                    if self.match_next('{')? {
+                        self.interpolation_token()?;
+                    } else {
+                        self.variable_token(true)?;
+                    }
                 }
-                _ => continue,
+                _ => fragment.push(ch),
             }
         }
-        self.add_token(TokenType::VALUE);
+        self.add_token_with_value(TokenType::VALUE, fragment);
         Ok(())
     }
 
-    fn math_token(&mut self) -> Result<(), CodeError> {
-        self.skip_spaces()?;
-        if self.is_at_end() || self.tokens.last().unwrap().token_type == TokenType::NEWLINE {
-            return Ok(());
-        }
-        match self.advance("Expected Math expression")? {
-            '+' => self.add_token(TokenType::ADD),
-            '-' => self.add_token(TokenType::SUB),
-            '*' => self.add_token(TokenType::MULT),
-            '/' => self.add_token(TokenType::DIV),
+    fn escape_sequence(&mut self) -> Result<char, CodeError> {
+        let escape_start = self.current - 1;
+        let ch = match self.advance("Expected escape sequence after \\")? {
+            'n' => '\n',
+            't' => '\t',
+            '\\' => '\\',
+            '$' => '$',
+            '"' => '"',
+            'u' => {
+                if !self.match_next('{')? {
+                    return Err(CodeError::new(
+                        self.line,
+                        escape_start,
+                        self.current,
+                        "Expected { after \\u",
+                    ));
+                }
+                let mut hex = String::new();
+                while !self.match_next('}')? {
+                    let digit = self.advance("Expected closing } for \\u{...}")?;
+                    if !digit.is_ascii_hexdigit() || hex.len() >= 6 {
+                        return Err(CodeError::new(
+                            self.line,
+                            escape_start,
+                            self.current,
+                            "Expected up to 6 hex digits in \\u{...}",
+                        ));
+                    }
+                    hex.push(digit);
+                }
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        CodeError::new(
+                            self.line,
+                            escape_start,
+                            self.current,
+                            format!("\\u{{{}}} is not a valid unicode character", hex).as_str(),
+                        )
+                    })?
+            }
             _ => {
                 return Err(CodeError::new(
                     self.line,
-                    self.start,
+                    escape_start,
                     self.current,
-                    "Expected Math Operator (+,-,*,/)",
+                    "Unknown escape sequence. Expected \\n, \\t, \\\\, \\$, \\\" or \\u{...}",
                 ))
             }
+        };
+        Ok(ch)
+    }
+
+    /// Lexes an expression made of numbers, variables, quoted texts and operators
+    /// up to the end of the line.
+    fn expression_token(&mut self) -> Result<(), CodeError> {
+        self.expression_until(&[])
+    }
+
+    /// Like `expression_token`, but also stops in front of any of the `stops` characters.
+    fn expression_until(&mut self, stops: &[char]) -> Result<(), CodeError> {
+        let mut depth = 0_usize;
+        loop {
+            self.skip_line_spaces()?;
+            if self.is_at_end() {
+                return Ok(());
+            }
+            let ch = self.get_char_at_current("Expected expression")?;
+            if ch == '\n' || (depth == 0 && stops.contains(&ch)) {
+                return Ok(());
+            }
+            if self.is_next("//") {
+                return self.comment_token();
+            }
+            match self.advance("Expected expression")? {
+                '+' => self.add_token(TokenType::ADD),
+                '-' => self.add_token(TokenType::SUB),
+                '*' => self.add_token(TokenType::MULT),
+                '/' => self.add_token(TokenType::DIV),
+                '&' => self.add_token(TokenType::CONCAT),
+                ',' => self.add_token(TokenType::COMMA),
+                ':' => self.add_token(TokenType::COLON),
+                '<' if self.match_next('=')? => self.add_token(TokenType::LESSEQUAL),
+                '<' => self.add_token(TokenType::LESS),
+                '>' if self.match_next('=')? => self.add_token(TokenType::GREATEREQUAL),
+                '>' => self.add_token(TokenType::GREATER),
+                '=' if self.match_next('=')? => self.add_token(TokenType::EQUALEQUAL),
+                '!' if self.match_next('=')? => self.add_token(TokenType::NOTEQUAL),
+                '.' => {
+                    self.add_token(TokenType::DOT);
+                    self.identifier_token()?;
+                }
+                '(' => {
+                    depth += 1;
+                    self.add_token(TokenType::LPAREN);
+                }
+                ')' => {
+                    depth = depth.saturating_sub(1);
+                    self.add_token(TokenType::RPAREN);
+                }
+                '{' => {
+                    depth += 1;
+                    self.add_token(TokenType::LBRACE);
+                }
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    self.add_token(TokenType::RBRACE);
+                }
+                '[' => {
+                    depth += 1;
+                    self.add_token(TokenType::LBRACKET);
+                }
+                ']' => {
+                    depth = depth.saturating_sub(1);
+                    self.add_token(TokenType::RBRACKET);
+                }
+                '"' => self.quoted_token()?,
+                '$' => self.variable_token(false)?,
+                ch if ch.is_ascii_digit() => self.digits_token()?,
+                ch if ch.is_alphabetic() || ch == '_' => {
+                    self.current -= 1;
+                    self.identifier_token()?;
+                    let is_call = self.is_next("(");
+                    let token = self.tokens.last_mut().unwrap();
+                    if is_call {
+                        token.token_type = TokenType::FUNCTION;
+                    } else if token.value == "enthält" {
+                        token.token_type = TokenType::CONTAINS;
+                    }
+                }
+                _ => {
+                    return Err(CodeError::new(
+                        self.line,
+                        self.start,
+                        self.current,
+                        "Expected digit, variable, text in quotes, comma, bracket, enthält or operator (+,-,*,/,&,==,!=,<,<=,>,>=)",
+                    ))
+                }
+            }
         }
-        self.number_token()?;
+    }
+
+    /// Lexes a number like `42` or a Kommazahl like `3.14`, the first digit is already consumed.
+    fn digits_token(&mut self) -> Result<(), CodeError> {
+        while !self.is_at_end() && self.get_char_at_current("")?.is_ascii_digit() {
+            self.current += 1;
+        }
+        let has_fraction = self.is_next(".")
+            && self
+                .input
+                .chars()
+                .nth(self.current + 1)
+                .is_some_and(|ch| ch.is_ascii_digit());
+        if has_fraction {
+            self.current += 1;
+            while !self.is_at_end() && self.get_char_at_current("")?.is_ascii_digit() {
+                self.current += 1;
+            }
+        }
+        self.add_token(TokenType::VALUE);
         Ok(())
     }
 
-    fn number_token(&mut self) -> Result<(), CodeError> {
-        self.skip_spaces()?;
-        if self.is_at_end() || self.tokens.last().unwrap().token_type == TokenType::NEWLINE {
-            return Ok(());
+    /// Lexes the `name$` part of a `$name$` reference, the opening `$` is already consumed.
+    /// Inside texts the name may be followed by a format specifier like `$preis:.2$`.
+    fn variable_token(&mut self, allow_format: bool) -> Result<(), CodeError> {
+        self.start = self.current;
+        self.identifier_token()?;
+        self.accessor_tokens()?;
+        if allow_format {
+            self.format_token('$')?;
         }
-        let mut ch = self.advance("Expected Number value")?;
+        if !self.match_next('$')? {
+            return Err(CodeError::new(
+                self.line,
+                self.start,
+                self.current,
+                "Expected closing $ for variable",
+            ));
+        } else if self.match_next('$')? {
+            return Err(CodeError::new(
+                self.line,
+                self.start,
+                self.current,
+                "Found $$ but expected $ because of present variable",
+            ));
+        }
+        self.start = self.current;
+        Ok(())
+    }
 
-        if ch.is_digit(10) {
-            // Numeric value handling
-            while ch.is_digit(10) && !self.is_at_end() {
-                ch = self.advance("Unexpected ending of Number")?;
+    /// Lexes `[index]`, `[start:end]` and `.feld` accessors directly following a variable name.
+    fn accessor_tokens(&mut self) -> Result<(), CodeError> {
+        while self.is_next("[") || self.is_next(".") {
+            self.current += 1;
+            if self.input.chars().nth(self.current - 1) == Some('.') {
+                self.add_token(TokenType::DOT);
+                self.identifier_token()?;
+                continue;
             }
-            self.current -= 1;
-            self.add_token(TokenType::VALUE);
-        } else if ch == '$' {
-            self.start = self.current;
-            self.identifier_token()?;
-            if !self.match_next('$')? {
-                return Err(CodeError::new(
-                    self.line,
-                    self.start,
-                    self.current,
-                    "Expected closing $ for variable",
-                ));
-            } else if self.match_next('$')? {
+            self.add_token(TokenType::LBRACKET);
+            self.expression_until(&[']'])?;
+            if !self.match_next(']')? {
                 return Err(CodeError::new(
                     self.line,
                     self.start,
                     self.current,
-                    "Found $$ but expected $ because of present variable",
+                    "Expected closing ] for index",
                 ));
             }
-        } else {
+            self.add_token(TokenType::RBRACKET);
+        }
+        Ok(())
+    }
+
+    /// Lexes the `expression}$` part of a `${expression}$` interpolation, the opening `${` is
+    /// already consumed.
+    fn interpolation_token(&mut self) -> Result<(), CodeError> {
+        self.add_token(TokenType::LBRACE);
+        self.expression_until(&['}', ':'])?;
+        self.format_token('}')?;
+        if !self.match_next('}')? || !self.match_next('$')? {
             return Err(CodeError::new(
                 self.line,
                 self.start,
                 self.current,
-                "Expected digit or variable in number token",
+                "Expected closing }$ for expression",
             ));
         }
-        self.math_token()?;
+        self.add_token(TokenType::RBRACE);
+        Ok(())
+    }
+
+    fn format_token(&mut self, end: char) -> Result<(), CodeError> {
+        if !self.match_next(':')? {
+            return Ok(());
+        }
+        self.start = self.current;
+        while !self.is_at_end() && !matches!(self.get_char_at_current("")?, '\n' | '$' | '}') {
+            self.current += 1;
+        }
+        if self.is_at_end() || self.get_char_at_current("")? != end {
+            return Err(CodeError::new(
+                self.line,
+                self.start,
+                self.current,
+                format!("Expected closing {} after format specifier", end).as_str(),
+            ));
+        }
+        self.add_token(TokenType::FORMAT);
         Ok(())
     }
 
@@ -207,23 +514,56 @@ impl Lexer {
         }
     }
 
+    /// Lexes `=`, `+=`, `-=`, `*=` or `/=` and returns false if none of them follows.
+    fn assignment_operator_token(&mut self) -> Result<bool, CodeError> {
+        let token_type = if self.is_next("=") {
+            TokenType::EQUAL
+        } else if self.is_next("+=") {
+            TokenType::ADDEQUAL
+        } else if self.is_next("-=") {
+            TokenType::SUBEQUAL
+        } else if self.is_next("*=") {
+            TokenType::MULTEQUAL
+        } else if self.is_next("/=") {
+            TokenType::DIVEQUAL
+        } else {
+            return Ok(false);
+        };
+        self.current += if token_type == TokenType::EQUAL { 1 } else { 2 };
+        self.add_token(token_type);
+        Ok(true)
+    }
+
     fn op_token(&mut self) -> Result<(), CodeError> {
         self.skip_spaces()?;
         if self.is_at_end() {
             return Ok(());
         }
-        match self.advance_space()?.as_ref() {
+        if self.is_next("//") {
+            return self.comment_token();
+        }
+        let word = self.advance_word()?.into_owned();
+        match word.as_str() {
             "Zahl" => {
-                self.add_token(TokenType::ZAHL);
+                self.keyword_token(TokenType::ZAHL)?;
                 self.identifier_token()?;
                 if self.is_assignment()? {
                     self.equal_token()?;
-                    self.number_token()?;
+                    self.expression_token()?;
+                }
+                Ok(())
+            }
+            "Kommazahl" => {
+                self.keyword_token(TokenType::KOMMAZAHL)?;
+                self.identifier_token()?;
+                if self.is_assignment()? {
+                    self.equal_token()?;
+                    self.expression_token()?;
                 }
                 Ok(())
             }
             "Text" => {
-                self.add_token(TokenType::TEXT);
+                self.keyword_token(TokenType::TEXT)?;
                 self.identifier_token()?;
                 if self.is_assignment()? {
                     self.equal_token()?;
@@ -232,24 +572,249 @@ impl Lexer {
                 Ok(())
             }
             "Input" => {
-                self.add_token(TokenType::INPUT);
+                self.keyword_token(TokenType::INPUT)?;
+                if self.match_keyword("Zahl") {
+                    self.keyword_token(TokenType::ZAHL)?;
+                } else if self.match_keyword("Kommazahl") {
+                    self.keyword_token(TokenType::KOMMAZAHL)?;
+                } else if self.match_keyword("Text") {
+                    self.keyword_token(TokenType::TEXT)?;
+                }
                 self.identifier_token()?;
+                self.expression_token()?;
                 Ok(())
             }
             "Output" => {
-                self.add_token(TokenType::OUTPUT);
+                self.keyword_token(TokenType::OUTPUT)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Liste" => {
+                self.keyword_token(TokenType::LISTE)?;
                 self.identifier_token()?;
+                if self.is_assignment()? {
+                    self.equal_token()?;
+                    self.expression_token()?;
+                }
                 Ok(())
             }
-            _ => Err(CodeError::new(
-                self.line,
-                self.start,
-                self.current,
-                "Expected Text, Zahl, Output or Input!",
-            )),
+            "Tabelle" => {
+                self.keyword_token(TokenType::TABELLE)?;
+                self.identifier_token()?;
+                if self.is_assignment()? {
+                    self.equal_token()?;
+                    self.expression_token()?;
+                }
+                Ok(())
+            }
+            "Konstante" => {
+                self.keyword_token(TokenType::CONST)?;
+                self.op_token()
+            }
+            "Struktur" => {
+                self.keyword_token(TokenType::STRUKTUR)?;
+                self.identifier_token()?;
+                let name = self.tokens.last().unwrap().value.to_owned();
+                self.skip_line_spaces()?;
+                if !self.match_next(':')? {
+                    return Err(CodeError::new(
+                        self.line,
+                        self.start,
+                        self.current,
+                        "Expected : after the name of the Struktur",
+                    ));
+                }
+                self.add_token(TokenType::COLON);
+                loop {
+                    self.skip_line_spaces()?;
+                    self.field_type_token()?;
+                    self.skip_line_spaces()?;
+                    self.identifier_token()?;
+                    self.skip_line_spaces()?;
+                    if !self.match_next(',')? {
+                        break;
+                    }
+                    self.add_token(TokenType::COMMA);
+                }
+                self.struct_names.insert(name);
+                Ok(())
+            }
+            "Hinzufügen" => {
+                self.keyword_token(TokenType::APPEND)?;
+                self.identifier_token()?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Entfernen" => {
+                self.keyword_token(TokenType::REMOVE)?;
+                self.identifier_token()?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Für" => {
+                self.keyword_token(TokenType::FOR)?;
+                if !self.match_keyword("jedes") {
+                    return Err(CodeError::new(
+                        self.line,
+                        self.start,
+                        self.current,
+                        "Expected 'jedes' after Für",
+                    ));
+                }
+                self.skip_line_spaces()?;
+                self.identifier_token()?;
+                self.skip_line_spaces()?;
+                if self.match_next(',')? {
+                    self.add_token(TokenType::COMMA);
+                    self.skip_line_spaces()?;
+                    self.identifier_token()?;
+                    self.skip_line_spaces()?;
+                }
+                if !self.match_keyword("in") {
+                    return Err(CodeError::new(
+                        self.line,
+                        self.start,
+                        self.current + 1,
+                        "Expected 'in' after the loop variable",
+                    ));
+                }
+                self.keyword_token(TokenType::IN)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Ende" => {
+                self.keyword_token(TokenType::END)?;
+                Ok(())
+            }
+            "Versuche" => {
+                self.keyword_token(TokenType::TRY)?;
+                Ok(())
+            }
+            "Fehler" => {
+                self.keyword_token(TokenType::CATCH)?;
+                self.identifier_token()?;
+                Ok(())
+            }
+            "Wirf" => {
+                self.keyword_token(TokenType::THROW)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Lese" => {
+                self.keyword_token(TokenType::READ)?;
+                self.identifier_token()?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Schreibe" => {
+                self.keyword_token(TokenType::WRITE)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Anhängen" => {
+                self.keyword_token(TokenType::APPENDFILE)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Lade" => {
+                self.keyword_token(TokenType::IMPORT)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Beenden" => {
+                self.keyword_token(TokenType::EXIT)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "Prüfe" => {
+                self.keyword_token(TokenType::ASSERT)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            "OutputOhneZeilenumbruch" => {
+                self.keyword_token(TokenType::OUTPUTNONEWLINE)?;
+                self.expression_token()?;
+                Ok(())
+            }
+            name if self.struct_names.contains(name) => {
+                self.keyword_token(TokenType::TYPENAME)?;
+                self.identifier_token()?;
+                if self.is_assignment()? {
+                    self.equal_token()?;
+                    self.expression_token()?;
+                }
+                Ok(())
+            }
+            _ => {
+                let (word_start, word_end) = (self.start, self.current);
+                // Lines starting with a variable change it, e.g. `x = 1`, `x += 1` or `x[0] = 1`
+                self.current = self.start;
+                if self.identifier_token().is_ok() {
+                    self.accessor_tokens()?;
+                    self.skip_line_spaces()?;
+                    if self.assignment_operator_token()? {
+                        self.expression_token()?;
+                        return Ok(());
+                    }
+                }
+                let error = CodeError::new(
+                    self.line,
+                    word_start,
+                    word_end,
+                    "Expected Text, Zahl, Kommazahl, Liste, Tabelle, Struktur, Konstante, Output, OutputOhneZeilenumbruch, Input, Hinzufügen, Entfernen, Für, Versuche, Wirf, Prüfe, Lese, Schreibe, Anhängen, Beenden, Lade or Ende!",
+                );
+                Err(match keyword_hint(&word) {
+                    Some(hint) => error.with_help(&hint),
+                    None => error.with_suggestion(
+                        &word,
+                        KEYWORDS
+                            .iter()
+                            .copied()
+                            .chain(self.struct_names.iter().map(String::as_str)),
+                    ),
+                })
+            }
         }
     }
 
+    /// Lexes a comment from `//` up to the end of the line.
+    fn comment_token(&mut self) -> Result<(), CodeError> {
+        while !self.is_at_end() && self.get_char_at_current("")? != '\n' {
+            self.current += 1;
+        }
+        self.add_token(TokenType::COMMENT);
+        Ok(())
+    }
+
+    /// Lexes the type of a field in a `Struktur` declaration.
+    fn field_type_token(&mut self) -> Result<(), CodeError> {
+        self.start = self.current;
+        self.identifier_token()?;
+        let token = self.tokens.last_mut().unwrap();
+        token.token_type = match token.value.as_str() {
+            "Text" => TokenType::TEXT,
+            "Zahl" => TokenType::ZAHL,
+            "Kommazahl" => TokenType::KOMMAZAHL,
+            "Liste" => TokenType::LISTE,
+            "Tabelle" => TokenType::TABELLE,
+            name if self.struct_names.contains(name) => TokenType::TYPENAME,
+            _ => {
+                return Err(CodeError::new(
+                    token.line,
+                    token.start,
+                    token.end,
+                    "Expected a field type (Text, Zahl, Kommazahl, Liste, Tabelle or a declared Struktur)",
+                ))
+            }
+        };
+        Ok(())
+    }
+
+    fn keyword_token(&mut self, token_type: TokenType) -> Result<(), CodeError> {
+        self.add_token(token_type);
+        self.skip_line_spaces()
+    }
+
     fn is_assignment(&mut self) -> Result<bool, CodeError> {
         self.skip_spaces()?;
         if self.tokens.last().unwrap().token_type == TokenType::NEWLINE {
@@ -267,11 +832,15 @@ impl Lexer {
             .skip(self.start)
             .take(self.current - self.start)
             .collect();
+        self.add_token_with_value(token_type, slice);
+    }
+
+    fn add_token_with_value(&mut self, token_type: TokenType, value: String) {
         self.tokens.push(Token::new(
             self.line,
             self.start,
             self.current,
-            slice.to_owned(),
+            value,
             token_type,
         ));
         self.start = self.current;
@@ -316,21 +885,50 @@ impl Lexer {
         Ok(())
     }
 
-    fn advance_space(&mut self) -> Result<Cow<str>, CodeError> {
-        while let Ok(char) = self.advance("Expected Space at the end!") {
-            if char == ' ' {
-                break;
-            }
+    fn skip_line_spaces(&mut self) -> Result<(), CodeError> {
+        while !self.is_at_end() && matches!(self.get_char_at_current("")?, ' ' | '\t') {
+            self.current += 1;
+        }
+        self.start = self.current;
+        Ok(())
+    }
+
+    fn advance_word(&mut self) -> Result<Cow<'_, str>, CodeError> {
+        while !self.is_at_end() && !self.get_char_at_current("")?.is_whitespace() {
+            self.current += 1;
         }
         let slice: String = self
             .input
             .chars()
             .skip(self.start)
-            .take(self.current - 1 - self.start)
+            .take(self.current - self.start)
             .collect();
         Ok(slice.into())
     }
 
+    /// Consumes `keyword` if it follows as a whole word.
+    fn match_keyword(&mut self, keyword: &str) -> bool {
+        let keyword_len = keyword.chars().count();
+        let is_word_end = match self.input.chars().nth(self.current + keyword_len) {
+            Some(ch) => ch.is_whitespace(),
+            None => true,
+        };
+        if self.is_next(keyword) && is_word_end {
+            self.current += keyword_len;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_next(&self, expected: &str) -> bool {
+        self.input
+            .chars()
+            .skip(self.current)
+            .take(expected.chars().count())
+            .eq(expected.chars())
+    }
+
     fn get_char_at_current(&self, err_msg: &str) -> Result<char, CodeError> {
         if let Some(char) = self.input.chars().nth(self.current) {
             Ok(char)
@@ -349,3 +947,186 @@ impl Lexer {
         self.current >= self.input.chars().count()
     }
 }
+
+/// Hint for a word at the start of a line that is often written instead of a keyword.
+fn keyword_hint(word: &str) -> Option<String> {
+    let lowercase = word.to_lowercase();
+    if let Some(keyword) = KEYWORDS
+        .iter()
+        .find(|keyword| keyword.to_lowercase() == lowercase)
+    {
+        return Some(format!(
+            "Keywords start with a capital letter, write {} instead of {}",
+            keyword, word
+        ));
+    }
+    let hint = match lowercase.as_str() {
+        "print" | "println" | "echo" | "ausgabe" => "Values are printed with Output",
+        "eingabe" | "read" | "readline" => "Values are read with Input",
+        "int" | "integer" | "number" => "Whole numbers are declared with Zahl",
+        "float" | "double" | "decimal" => "Decimal numbers are declared with Kommazahl",
+        "string" | "str" => "Texts are declared with Text",
+        "let" | "var" | "const" => {
+            "Variables are declared with their type like Zahl x = 1, constants with Konstante"
+        }
+        "for" | "foreach" | "fuer" | "while" => "Loops are written as Für jedes x in liste",
+        "try" => "Errors are caught with Versuche, Fehler e and Ende",
+        "import" | "include" | "require" => "Other files are loaded with Lade \"datei.red\"",
+        "exit" | "return" => "The script is stopped with Beenden",
+        _ => return None,
+    };
+    Some(hint.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lexer;
+    use crate::{
+        lexer::{Token, TokenType},
+        parser::{FormatAlign, FormatSpec},
+    };
+
+    fn lex(source: &str) -> Vec<Token> {
+        Lexer::new(source).lex().unwrap()
+    }
+
+    fn format_values(source: &str) -> Vec<String> {
+        lex(source)
+            .into_iter()
+            .filter(|token| token.token_type == TokenType::FORMAT)
+            .map(|token| token.value)
+            .collect()
+    }
+
+    /// The text of all VALUE tokens, with the escape sequences resolved.
+    fn text(source: &str) -> String {
+        lex(source)
+            .into_iter()
+            .filter(|token| token.token_type == TokenType::VALUE)
+            .map(|token| token.value)
+            .collect()
+    }
+
+    fn lex_error(source: &str) -> String {
+        Lexer::new(source).lex().unwrap_err().msg().to_owned()
+    }
+
+    fn spec(value: &str) -> Result<FormatSpec, String> {
+        let token = Token::new(1, 0, value.len(), value.to_owned(), TokenType::FORMAT);
+        FormatSpec::from_token(&token).map_err(|err| err.msg().to_owned())
+    }
+
+    #[test]
+    fn format_specs_are_lexed_after_variables_and_expressions() {
+        assert_eq!(
+            format_values("Text t = $preis:.2$ und ${a / b:>8}$\n"),
+            [".2", ">8"]
+        );
+        assert_eq!(format_values("Output \"$n:05$\"\n"), ["05"]);
+        assert!(format_values("Text t = $preis$\n").is_empty());
+    }
+
+    #[test]
+    fn unclosed_format_spec_is_an_error() {
+        let err = Lexer::new("Text t = $preis:.2\n").lex().unwrap_err();
+        assert_eq!(err.msg(), "Expected closing $ after format specifier");
+    }
+
+    #[test]
+    fn format_specs_are_parsed() {
+        let parsed = spec("*^20.3").unwrap();
+        assert_eq!(parsed.fill, '*');
+        assert_eq!(parsed.align, Some(FormatAlign::Center));
+        assert_eq!(parsed.width, 20);
+        assert_eq!(parsed.precision, Some(3));
+
+        let parsed = spec("05").unwrap();
+        assert!(parsed.zero_pad);
+        assert_eq!(parsed.width, 5);
+        assert!(parsed.align.is_none());
+
+        let parsed = spec("<7").unwrap();
+        assert_eq!(parsed.fill, ' ');
+        assert_eq!(parsed.align, Some(FormatAlign::Left));
+    }
+
+    #[test]
+    fn invalid_format_specs_are_errors() {
+        for invalid in [".", "5x", ">.", "abc"] {
+            assert!(spec(invalid)
+                .unwrap_err()
+                .starts_with("Invalid format specifier"));
+        }
+    }
+
+    #[test]
+    fn huge_format_specs_are_errors() {
+        assert!(spec(".1000").is_ok());
+        for huge in [".1001", ">999999999999", ".99999999999999999999999"] {
+            assert_eq!(
+                spec(huge).unwrap_err(),
+                "Width and precision of a format specifier can be at most 1000"
+            );
+        }
+    }
+
+    #[test]
+    fn escape_sequences_are_resolved() {
+        assert_eq!(
+            text("Text s = a\\nb\\tc\\\\d\\$e\\\"f\n"),
+            "a\nb\tc\\d$e\"f"
+        );
+        assert_eq!(text("Text s = \\u{1F600} \\u{e4}\n"), "\u{1F600} ä");
+        assert_eq!(text("Text s = 5$$\n"), "5$");
+    }
+
+    #[test]
+    fn invalid_escape_sequences_are_errors() {
+        assert_eq!(
+            lex_error("Text s = a\\qb\n"),
+            "Unknown escape sequence. Expected \\n, \\t, \\\\, \\$, \\\" or \\u{...}"
+        );
+        assert_eq!(lex_error("Text s = \\u41\n"), "Expected { after \\u");
+        assert_eq!(
+            lex_error("Text s = \\u{1234567}\n"),
+            "Expected up to 6 hex digits in \\u{...}"
+        );
+        assert_eq!(
+            lex_error("Text s = \\u{D800}\n"),
+            "\\u{D800} is not a valid unicode character"
+        );
+    }
+
+    #[test]
+    fn quoted_texts_keep_their_spaces() {
+        assert_eq!(text("Text s =   ohne\n"), "ohne");
+        assert_eq!(text("Text s = \"  mit  \"\n"), "  mit  ");
+        assert_eq!(text("Text s = \"a \\\"b\\\" $$\"\n"), "a \"b\" $");
+        assert_eq!(
+            lex_error("Text s = \"offen\n"),
+            "Expected closing \" for text"
+        );
+    }
+
+    #[test]
+    fn multi_line_texts_lose_their_common_indentation() {
+        let source =
+            "Text brief = \"\"\"\n    Hallo,\n      eingerückt\n\n    \\tEnde\n    \"\"\"\n";
+        assert_eq!(text(source), "Hallo,\n  eingerückt\n\n\tEnde");
+        let source = "Text brief = \"\"\"\n  Hallo $name$!\n  \"\"\"\nOutput brief\n";
+        let tokens = lex(source);
+        assert!(tokens
+            .iter()
+            .any(|token| token.token_type == TokenType::IDENTIFIER && token.value == "name"));
+        assert_eq!(text(source), "Hallo !");
+        assert_eq!(text("Text leer = \"\"\"\n\"\"\"\n"), "");
+    }
+
+    #[test]
+    fn unclosed_multi_line_text_is_an_error() {
+        assert_eq!(
+            lex_error("Text brief = \"\"\"\n    Hallo\n"),
+            "Missing closing \"\"\" for this text"
+        );
+    }
+}
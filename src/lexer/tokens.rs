@@ -2,9 +2,15 @@
 pub enum TokenType {
     TEXT,
     ZAHL,
+    KOMMAZAHL,
     EQUAL,
+    ADDEQUAL,
+    SUBEQUAL,
+    MULTEQUAL,
+    DIVEQUAL,
     VALUE,
     OUTPUT,
+    OUTPUTNONEWLINE,
     INPUT,
     IDENTIFIER,
     NEWLINE,
@@ -13,6 +19,47 @@ pub enum TokenType {
     SUB,
     DIV,
     MULT,
+    CONCAT,
+    QUOTE,
+    LBRACE,
+    RBRACE,
+    FORMAT,
+    COMMA,
+    COLON,
+    LPAREN,
+    RPAREN,
+    LBRACKET,
+    RBRACKET,
+    FUNCTION,
+    LISTE,
+    TABELLE,
+    STRUKTUR,
+    CONST,
+    TYPENAME,
+    DOT,
+    CONTAINS,
+    APPEND,
+    REMOVE,
+    FOR,
+    IN,
+    END,
+    TRY,
+    CATCH,
+    THROW,
+    ASSERT,
+    EQUALEQUAL,
+    NOTEQUAL,
+    LESS,
+    LESSEQUAL,
+    GREATER,
+    GREATEREQUAL,
+    READ,
+    WRITE,
+    APPENDFILE,
+    EXIT,
+    IMPORT,
+    /// `// ...` up to the end of the line, skipped by the parser
+    COMMENT,
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,587 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use colored::Colorize;
+
+use crate::{
+    checker::TypeChecker,
+    error::CodeError,
+    lexer::{Lexer, Token, TokenType},
+    parser::{
+        Accessor, Expression, ExpressionTypes, FunctionCall, IdentifierExpression,
+        NumberExpression, NumberExpressionTypes, Parser, TextExpression, TextExpressionTypes,
+        ValueExpression, ValueExpressionTypes, VariableType,
+    },
+};
+
+/// Kinds of warnings, each can be allowed with a comment like `// lint: allow unused`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lint {
+    /// A variable that is declared but never used
+    Unused,
+    /// A variable whose value never reaches an `Output`, a file or another instruction
+    NeverOutput,
+    /// A declaration of a variable that already exists
+    Shadowed,
+    /// A Text from `Input` used as number without checking it with `IstZahl`
+    UncheckedInput,
+    /// A comparison or `Prüfe` that does not use any variable
+    ConstantCondition,
+    /// Lines after `Beenden` or `Wirf`
+    Unreachable,
+}
+
+impl Lint {
+    /// Name of the warning as written in `// lint: allow ...` comments.
+    pub fn name(&self) -> &str {
+        match self {
+            Lint::Unused => "unused",
+            Lint::NeverOutput => "never-output",
+            Lint::Shadowed => "shadowed",
+            Lint::UncheckedInput => "unchecked-input",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::Unreachable => "unreachable",
+        }
+    }
+}
+
+pub struct Warning {
+    pub lint: Lint,
+    pub error: CodeError,
+}
+
+/// Lints every file and prints its warnings. Returns whether all files are free of errors and
+/// warnings.
+pub fn lint_files(files: &[PathBuf]) -> bool {
+    let mut count = 0;
+    let mut success = true;
+    for file in files {
+        let Ok(content) = fs::read_to_string(file) else {
+            println!("Error while reading file '{}'", file.display());
+            success = false;
+            continue;
+        };
+        let mut source = content.replace("\r", "");
+        source.push('\n');
+        let name = file.display().to_string();
+        match lint_source(&source) {
+            Ok(warnings) => {
+                count += warnings.len();
+                for warning in warnings {
+                    let header = format!("warning[{}]", warning.lint.name());
+                    println!("{}", header.yellow().bold());
+                    let error = warning.error.in_file(&name, &source);
+                    print!("{}", error.render_warning(&source));
+                }
+            }
+            Err(err) => {
+                print!("{}", err.in_file(&name, &source).render(&source));
+                success = false;
+            }
+        }
+    }
+    println!("{} warnings", count);
+    success && count == 0
+}
+
+/// Lexes, parses and checks a script and returns its warnings that are not allowed by a
+/// comment.
+pub fn lint_source(source: &str) -> Result<Vec<Warning>, CodeError> {
+    let tokens = Lexer::new(source).lex()?;
+    let parsed = Parser::new(tokens.clone()).parse()?;
+    TypeChecker::new().check(&parsed)?;
+    let allowed = allowed_lints(&tokens);
+    let warnings = lint(&parsed)
+        .into_iter()
+        .filter(|warning| {
+            !allowed
+                .get(&warning.error.line())
+                .is_some_and(|lints| lints.contains(&warning.lint.name()))
+        })
+        .collect();
+    Ok(warnings)
+}
+
+/// Lints allowed by `// lint: allow unused, shadowed` comments, by line. A comment at the end
+/// of a line allows them for that line, a comment on its own line for the next one.
+fn allowed_lints(tokens: &[Token]) -> HashMap<usize, HashSet<&str>> {
+    let mut allowed: HashMap<usize, HashSet<&str>> = HashMap::new();
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.token_type != TokenType::COMMENT {
+            continue;
+        }
+        let Some(names) = token.value[2..].trim().strip_prefix("lint: allow") else {
+            continue;
+        };
+        let own_line = idx == 0 || tokens[idx - 1].token_type == TokenType::NEWLINE;
+        let line = if own_line { token.line + 1 } else { token.line };
+        allowed
+            .entry(line)
+            .or_default()
+            .extend(names.split([',', ' ']).filter(|name| !name.is_empty()));
+    }
+    allowed
+}
+
+/// Returns all warnings of a parsed script, ordered by their position.
+pub fn lint(expressions: &[Expression]) -> Vec<Warning> {
+    let mut linter = Linter::default();
+    linter.block(expressions);
+    linter.finish()
+}
+
+/// How a variable got its value, which decides the warnings that apply to it.
+#[derive(Clone, Copy, PartialEq)]
+enum Origin {
+    Declaration,
+    /// Loop variable of `Für jedes` or error of `Fehler`
+    Block,
+    /// `Input` of a Text
+    TextInput,
+    /// Namespace of `Lade`, which runs the file even if no Konstante is used
+    Import,
+}
+
+struct Variable<'a> {
+    var_expr: &'a IdentifierExpression,
+    origin: Origin,
+    read: bool,
+    /// The value reaches an `Output` or another instruction with an effect
+    live: bool,
+    /// Variables the value was calculated from
+    sources: Vec<usize>,
+    /// Checked with `IstZahl`, only used for `Input` of a Text
+    checked: bool,
+}
+
+#[derive(Default)]
+struct Linter<'a> {
+    /// Every declaration in the order of the script, a variable declared twice has two
+    variables: Vec<Variable<'a>>,
+    /// Index of the latest declaration of each name
+    current: HashMap<&'a str, usize>,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Linter<'a> {
+    fn block(&mut self, expressions: &'a [Expression]) {
+        let mut stop: Option<(&Expression, &str)> = None;
+        for expression in expressions {
+            if let Some((stop_expr, keyword)) = stop.take() {
+                self.warn(
+                    Lint::Unreachable,
+                    expression.line,
+                    expression.start,
+                    expression.end,
+                    format!(
+                        "This line is never reached because of {} in line {}",
+                        keyword, stop_expr.line
+                    ),
+                );
+            }
+            self.expression(expression);
+            stop = match expression.get_expression() {
+                ExpressionTypes::ExitStatement(_) => Some((expression, "Beenden")),
+                ExpressionTypes::ThrowStatement(_) => Some((expression, "Wirf")),
+                _ => None,
+            };
+        }
+    }
+
+    fn expression(&mut self, expression: &'a Expression) {
+        let mut uses = Uses::default();
+        match expression.get_expression() {
+            ExpressionTypes::TextAssignment(var_expr, value) => {
+                uses.text(value);
+                let sources = self.apply(&uses, false);
+                self.declare(var_expr, Origin::Declaration, sources);
+            }
+            ExpressionTypes::NumberAssignment(var_expr, value)
+            | ExpressionTypes::DecimalAssignment(var_expr, value) => {
+                uses.number(value);
+                let sources = self.apply(&uses, false);
+                self.declare(var_expr, Origin::Declaration, sources);
+            }
+            ExpressionTypes::ListAssignment(var_expr, value)
+            | ExpressionTypes::TableAssignment(var_expr, value)
+            | ExpressionTypes::RecordAssignment(_, var_expr, Some(value)) => {
+                uses.value(value);
+                let sources = self.apply(&uses, false);
+                self.declare(var_expr, Origin::Declaration, sources);
+            }
+            ExpressionTypes::RecordAssignment(_, var_expr, None) => {
+                self.declare(var_expr, Origin::Declaration, Vec::new());
+            }
+            ExpressionTypes::Assignment(var_expr, value)
+            | ExpressionTypes::ListAppend(var_expr, value)
+            | ExpressionTypes::ListRemove(var_expr, value) => {
+                // Changing a variable does not read it, but indices and the value are read
+                uses.accessors(var_expr);
+                uses.value(value);
+                let sources = self.apply(&uses, false);
+                self.change(var_expr, sources);
+            }
+            ExpressionTypes::InputStatement(var_expr, var_type, prompt) => {
+                if let Some(prompt) = prompt {
+                    uses.text(prompt);
+                }
+                self.apply(&uses, true);
+                let origin = match var_type {
+                    VariableType::Text => Origin::TextInput,
+                    _ => Origin::Declaration,
+                };
+                self.declare(var_expr, origin, Vec::new());
+            }
+            ExpressionTypes::OutputStatement(values, _) => {
+                for value in values {
+                    uses.value(value);
+                }
+                self.apply(&uses, true);
+            }
+            ExpressionTypes::ForEachStatement(var_expr, value_var_expr, iterable, body) => {
+                uses.value(iterable);
+                let sources = self.apply(&uses, false);
+                self.declare(var_expr, Origin::Block, sources.clone());
+                if let Some(value_var_expr) = value_var_expr {
+                    self.declare(value_var_expr, Origin::Block, sources);
+                }
+                self.block(body);
+            }
+            ExpressionTypes::StructDeclaration(_) => {}
+            ExpressionTypes::ConstantDeclaration(declaration) => self.expression(declaration),
+            ExpressionTypes::TryStatement(body, var_expr, handler) => {
+                self.block(body);
+                self.declare(var_expr, Origin::Block, Vec::new());
+                self.block(handler);
+            }
+            ExpressionTypes::ThrowStatement(value) => {
+                uses.value(value);
+                self.apply(&uses, true);
+            }
+            ExpressionTypes::AssertStatement(condition, message) => {
+                uses.value(condition);
+                let is_comparison = matches!(
+                    condition.get_expression(),
+                    ValueExpressionTypes::Number(n_expr) if matches!(
+                        n_expr.get_expression(),
+                        NumberExpressionTypes::Compare(..) | NumberExpressionTypes::Contains(..)
+                    )
+                );
+                // Comparisons are already reported on their own
+                if uses.is_constant() && !is_comparison {
+                    self.warn(
+                        Lint::ConstantCondition,
+                        condition.line,
+                        condition.start,
+                        condition.end,
+                        "This condition does not use any variable, so its result is always the same".to_owned(),
+                    );
+                }
+                if let Some(message) = message {
+                    uses.text(message);
+                }
+                self.apply(&uses, true);
+            }
+            ExpressionTypes::ReadFile(path, var_expr, _) => {
+                uses.value(path);
+                let sources = self.apply(&uses, false);
+                self.declare(var_expr, Origin::Declaration, sources);
+            }
+            ExpressionTypes::WriteFile(value, path, _) => {
+                uses.value(value);
+                uses.value(path);
+                self.apply(&uses, true);
+            }
+            ExpressionTypes::ExitStatement(code) => {
+                if let Some(code) = code {
+                    uses.number(code);
+                }
+                self.apply(&uses, true);
+            }
+            ExpressionTypes::Import(namespace, _) => {
+                self.declare(namespace, Origin::Import, Vec::new());
+            }
+        }
+    }
+
+    /// Records the variables read by an instruction and returns them. `effect` is set for
+    /// instructions like `Output`, which make the values of the variables matter.
+    fn apply(&mut self, uses: &Uses<'a>, effect: bool) -> Vec<usize> {
+        let mut sources = Vec::new();
+        for (var_expr, usage) in &uses.reads {
+            // Variables like `argumente` are not declared in the script
+            let Some(&idx) = self.current.get(var_expr.var_name.as_str()) else {
+                continue;
+            };
+            let variable = &mut self.variables[idx];
+            variable.read = true;
+            variable.live |= effect;
+            match usage {
+                Usage::Check => variable.checked = true,
+                Usage::Number if variable.origin == Origin::TextInput && !variable.checked => {
+                    // Only the first unchecked use is reported
+                    variable.checked = true;
+                    let declaration = variable.var_expr;
+                    self.warnings.push(Warning {
+                        lint: Lint::UncheckedInput,
+                        error: CodeError::new(
+                            var_expr.line,
+                            var_expr.start,
+                            var_expr.end,
+                            format!(
+                                "{} is a Text from Input and used as number without checking it with IstZahl, or use Input Zahl",
+                                var_expr.var_name
+                            )
+                            .as_str(),
+                        )
+                        .with_note(
+                            declaration.line,
+                            declaration.start,
+                            declaration.end,
+                            format!("{} is read here", declaration.var_name).as_str(),
+                        ),
+                    });
+                }
+                _ => {}
+            }
+            sources.push(idx);
+        }
+        for comparison in &uses.constant_comparisons {
+            self.warn(
+                Lint::ConstantCondition,
+                comparison.line,
+                comparison.start,
+                comparison.end,
+                "This comparison does not use any variable, so its result is always the same"
+                    .to_owned(),
+            );
+        }
+        sources
+    }
+
+    fn declare(&mut self, var_expr: &'a IdentifierExpression, origin: Origin, sources: Vec<usize>) {
+        if let Some(&idx) = self.current.get(var_expr.var_name.as_str()) {
+            let existing = &self.variables[idx];
+            // Loops and `Fehler` using the same name one after another are fine
+            let both_blocks = existing.origin == Origin::Block && origin == Origin::Block;
+            if !both_blocks && existing.origin != Origin::Import {
+                let old = existing.var_expr;
+                self.warnings.push(Warning {
+                    lint: Lint::Shadowed,
+                    error: CodeError::new(
+                        var_expr.line,
+                        var_expr.start,
+                        var_expr.end,
+                        format!(
+                            "{} is declared again and replaces the variable from line {}",
+                            var_expr.var_name, old.line
+                        )
+                        .as_str(),
+                    )
+                    .with_note(
+                        old.line,
+                        old.start,
+                        old.end,
+                        format!("{} was declared here", old.var_name).as_str(),
+                    ),
+                });
+            }
+        }
+        self.current
+            .insert(var_expr.var_name.as_str(), self.variables.len());
+        self.variables.push(Variable {
+            var_expr,
+            origin,
+            read: false,
+            live: false,
+            sources,
+            checked: false,
+        });
+    }
+
+    /// Records that a variable was changed with values from `sources`.
+    fn change(&mut self, var_expr: &IdentifierExpression, sources: Vec<usize>) {
+        if let Some(&idx) = self.current.get(var_expr.var_name.as_str()) {
+            self.variables[idx].sources.extend(sources);
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, line: usize, start: usize, end: usize, msg: String) {
+        self.warnings.push(Warning {
+            lint,
+            error: CodeError::new(line, start, end, &msg),
+        });
+    }
+
+    /// Reports the variables that are never used or whose value never has an effect.
+    fn finish(mut self) -> Vec<Warning> {
+        let mut pending: Vec<usize> = (0..self.variables.len())
+            .filter(|&idx| self.variables[idx].live)
+            .collect();
+        while let Some(idx) = pending.pop() {
+            for source in self.variables[idx].sources.clone() {
+                if !self.variables[source].live {
+                    self.variables[source].live = true;
+                    pending.push(source);
+                }
+            }
+        }
+
+        for variable in &self.variables {
+            let var_expr = variable.var_expr;
+            if var_expr.var_name.starts_with('_') || variable.origin == Origin::Import {
+                continue;
+            }
+            let (lint, msg) = if !variable.read {
+                (
+                    Lint::Unused,
+                    "is never used, start its name with _ if that is intended",
+                )
+            } else if !variable.live {
+                (
+                    Lint::NeverOutput,
+                    "gets a value, but it never reaches an Output, a file or another instruction",
+                )
+            } else {
+                continue;
+            };
+            self.warnings.push(Warning {
+                lint,
+                error: CodeError::new(
+                    var_expr.line,
+                    var_expr.start,
+                    var_expr.end,
+                    format!("{} {}", var_expr.var_name, msg).as_str(),
+                ),
+            });
+        }
+        self.warnings
+            .sort_by_key(|warning| (warning.error.line(), warning.error.start()));
+        self.warnings
+    }
+}
+
+/// How a variable is read.
+enum Usage {
+    Value,
+    /// In a calculation or `AlsZahl`, where a Text has to be a number
+    Number,
+    /// As argument of `IstZahl`
+    Check,
+}
+
+/// Variables read by an expression.
+#[derive(Default)]
+struct Uses<'a> {
+    reads: Vec<(&'a IdentifierExpression, Usage)>,
+    calls: usize,
+    /// Comparisons of two values without any variable or call
+    constant_comparisons: Vec<&'a NumberExpression>,
+}
+
+impl<'a> Uses<'a> {
+    fn is_constant(&self) -> bool {
+        self.reads.is_empty() && self.calls == 0
+    }
+
+    fn value(&mut self, v_expr: &'a ValueExpression) {
+        match v_expr.get_expression() {
+            ValueExpressionTypes::Identifier(var_expr) => self.identifier(var_expr, Usage::Value),
+            ValueExpressionTypes::Call(call) => self.call(call),
+            ValueExpressionTypes::Number(n_expr) => self.number(n_expr),
+            ValueExpressionTypes::Text(t_expr) => self.text(t_expr),
+            ValueExpressionTypes::List(items) => {
+                for item in items {
+                    self.value(item);
+                }
+            }
+            ValueExpressionTypes::Table(entries) => {
+                for (key, value) in entries {
+                    self.value(key);
+                    self.value(value);
+                }
+            }
+        }
+    }
+
+    fn number(&mut self, n_expr: &'a NumberExpression) {
+        match n_expr.get_expression() {
+            NumberExpressionTypes::Value(_) | NumberExpressionTypes::Decimal(_) => {}
+            NumberExpressionTypes::Identifier(var_expr) => self.identifier(var_expr, Usage::Number),
+            NumberExpressionTypes::Add(lhs, rhs)
+            | NumberExpressionTypes::Sub(lhs, rhs)
+            | NumberExpressionTypes::Mult(lhs, rhs)
+            | NumberExpressionTypes::Div(lhs, rhs) => {
+                self.number(lhs);
+                self.number(rhs);
+            }
+            NumberExpressionTypes::Call(call) => self.call(call),
+            NumberExpressionTypes::Contains(lhs, rhs)
+            | NumberExpressionTypes::Compare(_, lhs, rhs) => {
+                let (reads, calls) = (self.reads.len(), self.calls);
+                self.value(lhs);
+                self.value(rhs);
+                if self.reads.len() == reads && self.calls == calls {
+                    self.constant_comparisons.push(n_expr);
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, t_expr: &'a TextExpression) {
+        match t_expr.get_expression() {
+            TextExpressionTypes::Concat(lhs, rhs) => {
+                self.text(lhs);
+                self.text(rhs);
+            }
+            TextExpressionTypes::Value(_) => {}
+            TextExpressionTypes::Identifier(var_expr) => self.identifier(var_expr, Usage::Value),
+            TextExpressionTypes::Number(n_expr) => self.number(n_expr),
+            TextExpressionTypes::Formatted(t_expr, _) => self.text(t_expr),
+            TextExpressionTypes::Call(call) => self.call(call),
+        }
+    }
+
+    fn identifier(&mut self, var_expr: &'a IdentifierExpression, usage: Usage) {
+        self.reads.push((var_expr, usage));
+        self.accessors(var_expr);
+    }
+
+    /// Reads the indices of `liste[i]` and `liste[a:b]`.
+    fn accessors(&mut self, var_expr: &'a IdentifierExpression) {
+        for accessor in &var_expr.accessors {
+            match accessor {
+                Accessor::Index(index) => self.value(index),
+                Accessor::Slice(from, to) => {
+                    for bound in [from, to].into_iter().flatten() {
+                        self.value(bound);
+                    }
+                }
+                Accessor::Field(_) => {}
+            }
+        }
+    }
+
+    fn call(&mut self, call: &'a FunctionCall) {
+        self.calls += 1;
+        let usage = match call.name.var_name.as_str() {
+            "AlsZahl" | "AlsKommazahl" => Some(Usage::Number),
+            "IstZahl" => Some(Usage::Check),
+            _ => None,
+        };
+        for arg in &call.args {
+            match (arg.get_expression(), &usage) {
+                (ValueExpressionTypes::Identifier(var_expr), Some(Usage::Number)) => {
+                    self.identifier(var_expr, Usage::Number)
+                }
+                (ValueExpressionTypes::Identifier(var_expr), Some(Usage::Check)) => {
+                    self.identifier(var_expr, Usage::Check)
+                }
+                _ => self.value(arg),
+            }
+        }
+    }
+}
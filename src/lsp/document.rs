@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::{
+    error::CodeError,
+    lexer::{Lexer, Token, TokenType},
+    linter::lint_source,
+};
+
+/// A declared variable, found from the tokens alone so it is also known while a later line
+/// does not parse.
+pub struct Symbol {
+    pub name: String,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    /// The declaration as shown on hover, e.g. `Zahl alter`
+    pub description: String,
+    /// Name of a file loaded with `Lade`, whose namespace can not be renamed
+    pub import: bool,
+}
+
+/// An open file with the results of lexing it.
+pub struct Document {
+    pub text: String,
+    chars: Vec<char>,
+    /// Index of the first character of every line
+    line_starts: Vec<usize>,
+    /// Tokens of the text, empty if it does not lex
+    tokens: Vec<Token>,
+    pub symbols: Vec<Symbol>,
+    pub structs: Vec<String>,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Self {
+        let mut text = text.replace("\r", "");
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            chars
+                .iter()
+                .enumerate()
+                .filter(|(_, ch)| **ch == '\n')
+                .map(|(idx, _)| idx + 1),
+        );
+        let tokens = Lexer::new(&text).lex().unwrap_or_default();
+        let mut document = Document {
+            text,
+            chars,
+            line_starts,
+            tokens,
+            symbols: Vec::new(),
+            structs: Vec::new(),
+        };
+        document.find_symbols();
+        document
+    }
+
+    /// Errors of the lexer, the parser and the type checker, or the warnings of `red lint`.
+    pub fn diagnostics(&self) -> Vec<Value> {
+        let diagnostic = |err: &CodeError, severity: u8| {
+            let mut message = err.msg().to_owned();
+            for help in err.help() {
+                message.push_str("\nhelp: ");
+                message.push_str(help);
+            }
+            json!({
+                "range": self.error_range(err),
+                "severity": severity,
+                "source": "red",
+                "message": message,
+            })
+        };
+        match lint_source(&self.text) {
+            Ok(warnings) => warnings
+                .iter()
+                .map(|warning| {
+                    let mut value = diagnostic(&warning.error, 2);
+                    value["code"] = json!(warning.lint.name());
+                    value
+                })
+                .collect(),
+            Err(err) => vec![diagnostic(&err, 1)],
+        }
+    }
+
+    /// Range of an error. Like `render_snippet`, a start before the line is a column.
+    fn error_range(&self, err: &CodeError) -> Value {
+        let line = err.line().clamp(1, self.line_starts.len());
+        let line_start = self.line_starts[line - 1];
+        let start = match err.start() < line_start {
+            true => line_start + err.start(),
+            false => err.start(),
+        };
+        let end = start + err.end().saturating_sub(err.start());
+        self.range(line, start, end)
+    }
+
+    /// LSP range of the characters `start..end` that start in `line`, counted from 1.
+    pub fn range(&self, line: usize, start: usize, end: usize) -> Value {
+        json!({
+            "start": self.position(line, start),
+            "end": self.position(line, end),
+        })
+    }
+
+    /// LSP position of a character, which counts lines from 0 and columns in UTF-16 units.
+    fn position(&self, line: usize, offset: usize) -> Value {
+        // A span may reach into the next lines, e.g. a text between `"""` lines
+        let line = self.line_starts[..self.line_starts.len() - 1]
+            .iter()
+            .rposition(|line_start| *line_start <= offset)
+            .map_or(line, |idx| idx + 1);
+        let line_start = self.line_starts[line - 1];
+        let offset = offset.clamp(line_start, self.chars.len());
+        let character: usize = self.chars[line_start..offset]
+            .iter()
+            .map(|ch| ch.len_utf16())
+            .sum();
+        json!({ "line": line - 1, "character": character })
+    }
+
+    /// Index of the character at an LSP position.
+    pub fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let line_start = *self.line_starts.get(line)?;
+        let mut units = 0;
+        let mut offset = line_start;
+        while offset < self.chars.len() && self.chars[offset] != '\n' && units < character {
+            units += self.chars[offset].len_utf16();
+            offset += 1;
+        }
+        Some(offset)
+    }
+
+    /// Line of a character, counted from 1.
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.line_starts
+            .partition_point(|line_start| *line_start <= offset)
+    }
+
+    /// The variable at or directly before the cursor.
+    pub fn variable_at(&self, offset: usize) -> Option<&Token> {
+        (0..self.tokens.len())
+            .filter(|&idx| self.is_variable(idx))
+            .map(|idx| &self.tokens[idx])
+            .find(|token| token.start <= offset && offset <= token.end)
+    }
+
+    /// Every use and declaration of the variable `name`.
+    pub fn variable_tokens(&self, name: &str) -> Vec<&Token> {
+        (0..self.tokens.len())
+            .filter(|&idx| self.is_variable(idx) && self.tokens[idx].value == name)
+            .map(|idx| &self.tokens[idx])
+            .collect()
+    }
+
+    /// The declaration a variable refers to: the last one before it, or the first one if it
+    /// is only declared later, e.g. in a loop.
+    pub fn definition(&self, token: &Token) -> Option<&Symbol> {
+        let mut symbols = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.name == token.value);
+        let first = symbols.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(symbols)
+                .rfind(|symbol| symbol.start <= token.start)
+                .unwrap_or(first),
+        )
+    }
+
+    /// Whether the token is a variable and not a field, a Struktur declaration or part of an
+    /// instruction like the `datei` in `Lese datei`.
+    fn is_variable(&self, idx: usize) -> bool {
+        let token = &self.tokens[idx];
+        if token.token_type != TokenType::IDENTIFIER {
+            return false;
+        }
+        let statement_start = self.tokens[..idx]
+            .iter()
+            .rposition(|token| token.token_type == TokenType::NEWLINE)
+            .map_or(0, |newline| newline + 1);
+        let instruction = self.tokens[statement_start..]
+            .iter()
+            .find(|token| token.token_type != TokenType::CONST)
+            .map(|token| &token.token_type);
+        let prev = idx.checked_sub(1).map(|prev| &self.tokens[prev].token_type);
+        let is_file_keyword = token.value == "in"
+            && matches!(
+                instruction,
+                Some(TokenType::READ | TokenType::WRITE | TokenType::APPENDFILE)
+            );
+        !matches!(prev, Some(TokenType::DOT | TokenType::READ))
+            && instruction != Some(&TokenType::STRUKTUR)
+            && !is_file_keyword
+    }
+
+    fn find_symbols(&mut self) {
+        let tokens = std::mem::take(&mut self.tokens);
+        for statement in tokens.split(|token| token.token_type == TokenType::NEWLINE) {
+            let statement: Vec<&Token> = statement
+                .iter()
+                .filter(|token| !matches!(token.token_type, TokenType::COMMENT | TokenType::EOF))
+                .collect();
+            let (constant, statement) = match statement.split_first() {
+                Some((first, rest)) if first.token_type == TokenType::CONST => (true, rest),
+                _ => (false, &statement[..]),
+            };
+            self.statement_symbols(statement, constant);
+        }
+        self.tokens = tokens;
+    }
+
+    fn statement_symbols(&mut self, statement: &[&Token], constant: bool) {
+        use TokenType::*;
+        let Some(first) = statement.first() else {
+            return;
+        };
+        let is_identifier = |idx: usize| {
+            statement
+                .get(idx)
+                .filter(|token| token.token_type == IDENTIFIER)
+                .copied()
+        };
+        match first.token_type {
+            TEXT | ZAHL | KOMMAZAHL | LISTE | TABELLE | TYPENAME => {
+                if let Some(var) = is_identifier(1) {
+                    let konstante = if constant { "Konstante " } else { "" };
+                    let description = format!("{}{} {}", konstante, first.value, var.value);
+                    self.add_symbol(var, description, false);
+                }
+            }
+            INPUT => {
+                let (var_type, idx) = match statement.get(1) {
+                    Some(token) if matches!(token.token_type, ZAHL | KOMMAZAHL | TEXT) => {
+                        (token.value.as_str(), 2)
+                    }
+                    _ => ("Text", 1),
+                };
+                if let Some(var) = is_identifier(idx) {
+                    let description = format!("{} {} (Input)", var_type, var.value);
+                    self.add_symbol(var, description, false);
+                }
+            }
+            FOR => {
+                let loop_vars = match (is_identifier(1), is_identifier(3)) {
+                    (Some(key), Some(value)) => vec![key, value],
+                    (Some(value), None) => vec![value],
+                    _ => Vec::new(),
+                };
+                let names: Vec<&str> = loop_vars.iter().map(|var| var.value.as_str()).collect();
+                for var in &loop_vars {
+                    self.add_symbol(var, format!("Für jedes {}", names.join(", ")), false);
+                }
+            }
+            CATCH => {
+                if let Some(var) = is_identifier(1) {
+                    self.add_symbol(var, format!("Fehler {}", var.value), false);
+                }
+            }
+            READ => {
+                let var_type = match is_identifier(1) {
+                    Some(mode) if mode.value == "zeilen" => "Liste",
+                    _ => "Text",
+                };
+                if let (true, Some(var)) = (statement.len() > 3, is_identifier(statement.len() - 1))
+                {
+                    self.add_symbol(var, format!("{} {}", var_type, var.value), false);
+                }
+            }
+            IMPORT => {
+                // The namespace is the name of the file, `Lade "mathe.red"` declares `mathe`
+                if let Some(file) = statement.get(2).filter(|token| token.token_type == VALUE) {
+                    let Some(name) = Path::new(&file.value).file_stem() else {
+                        return;
+                    };
+                    self.symbols.push(Symbol {
+                        name: name.to_string_lossy().into_owned(),
+                        line: file.line,
+                        start: file.start,
+                        end: file.end,
+                        description: format!("Lade \"{}\"", file.value),
+                        import: true,
+                    });
+                }
+            }
+            STRUKTUR => {
+                if let Some(name) = is_identifier(1) {
+                    self.structs.push(name.value.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_symbol(&mut self, var: &Token, description: String, import: bool) {
+        self.symbols.push(Symbol {
+            name: var.value.to_owned(),
+            line: var.line,
+            start: var.start,
+            end: var.end,
+            description,
+            import,
+        });
+    }
+}
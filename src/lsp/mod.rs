@@ -0,0 +1,4 @@
+mod document;
+mod server;
+pub use server::*;
+mod transport;
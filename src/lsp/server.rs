@@ -0,0 +1,442 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+use serde_json::{json, Value};
+
+use super::{
+    document::Document,
+    transport::{read_message, write_message},
+};
+use crate::{interpreter::FUNCTIONS, lexer::KEYWORDS};
+
+/// JSON-RPC error code for a message that is not valid JSON
+const PARSE_ERROR: i32 = -32700;
+/// JSON-RPC error code for requests the server does not know
+const METHOD_NOT_FOUND: i32 = -32601;
+/// JSON-RPC error code for a request that can not be answered, e.g. an invalid new name
+const REQUEST_FAILED: i32 = -32803;
+
+/// Answers LSP messages from `reader` until `exit`. Returns whether the client sent `shutdown`
+/// before, which is a clean end.
+pub fn serve(mut reader: impl BufRead, mut writer: impl Write) -> io::Result<bool> {
+    let mut server = Server {
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    while let Some(message) = read_message(&mut reader)? {
+        // Without an id the error can not be matched to a request, so it is sent with `null`
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": err.to_string() },
+                });
+                write_message(&mut writer, &response)?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, msg)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": msg },
+                    }),
+                };
+                write_message(&mut writer, &response)?;
+            }
+            None => {
+                for notification in server.notification(method, params) {
+                    write_message(&mut writer, &notification)?;
+                }
+            }
+        }
+    }
+    Ok(server.shutdown)
+}
+
+struct Server {
+    /// Open files by their URI
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i32, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    // The client always sends the whole text
+                    "textDocumentSync": 1,
+                    "completionProvider": {},
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "renameProvider": true,
+                },
+                "serverInfo": { "name": "red", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => Ok(self.completion(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/rename" => self.rename(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        }
+    }
+
+    /// Handles a notification and returns the notifications to send back.
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document::new(text);
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.to_owned(), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    /// The document and the index of the character at the position of a request.
+    fn document_at<'a>(&self, params: &'a Value) -> Option<(&'a str, &Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let offset = document.offset(&params["position"])?;
+        Some((uri, document, offset))
+    }
+
+    /// Keywords, the variables declared up to the line, built-in functions and Strukturen.
+    fn completion(&self, params: &Value) -> Value {
+        let Some((_, document, offset)) = self.document_at(params) else {
+            return json!([]);
+        };
+        let line = document.line_of(offset);
+        let mut items: Vec<Value> = KEYWORDS
+            .iter()
+            .map(|keyword| json!({ "label": keyword, "kind": 14 }))
+            .collect();
+        let mut variables: Vec<_> = document
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.line <= line)
+            .collect();
+        // The latest declaration of a name is the one in scope
+        variables.reverse();
+        let mut seen = Vec::new();
+        for symbol in variables {
+            if !seen.contains(&&symbol.name) {
+                seen.push(&symbol.name);
+                items.push(json!({
+                    "label": symbol.name,
+                    "kind": 6,
+                    "detail": symbol.description,
+                }));
+            }
+        }
+        items.extend(
+            FUNCTIONS
+                .iter()
+                .map(|function| json!({ "label": function, "kind": 3 })),
+        );
+        items.extend(
+            document
+                .structs
+                .iter()
+                .map(|name| json!({ "label": name, "kind": 22 })),
+        );
+        json!(items)
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((uri, document, offset)) = self.document_at(params) else {
+            return Value::Null;
+        };
+        let Some(symbol) = document
+            .variable_at(offset)
+            .and_then(|token| document.definition(token))
+        else {
+            return Value::Null;
+        };
+        json!({
+            "uri": uri,
+            "range": document.range(symbol.line, symbol.start, symbol.end),
+        })
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((_, document, offset)) = self.document_at(params) else {
+            return Value::Null;
+        };
+        let Some(token) = document.variable_at(offset) else {
+            return Value::Null;
+        };
+        let Some(symbol) = document.definition(token) else {
+            return Value::Null;
+        };
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```red\n{}\n```", symbol.description),
+            },
+            "range": document.range(token.line, token.start, token.end),
+        })
+    }
+
+    /// Renames every use and declaration of the variable at the position.
+    fn rename(&self, params: &Value) -> Result<Value, (i32, String)> {
+        let Some((uri, document, offset)) = self.document_at(params) else {
+            return Ok(Value::Null);
+        };
+        let Some(token) = document.variable_at(offset) else {
+            return Err((
+                REQUEST_FAILED,
+                "There is no variable to rename here".to_owned(),
+            ));
+        };
+        let new_name = params["newName"].as_str().unwrap_or_default();
+        let is_identifier = new_name
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+            && new_name.chars().all(|ch| ch.is_alphanumeric() || ch == '_');
+        if !is_identifier {
+            return Err((
+                REQUEST_FAILED,
+                format!("'{}' is not a valid variable name", new_name),
+            ));
+        }
+        if KEYWORDS
+            .iter()
+            .any(|keyword| keyword.eq_ignore_ascii_case(new_name))
+        {
+            return Err((
+                REQUEST_FAILED,
+                format!("'{}' is a keyword and can not be a variable name", new_name),
+            ));
+        }
+        // Renaming to an existing name would merge both variables
+        if new_name != token.value
+            && document
+                .symbols
+                .iter()
+                .any(|symbol| symbol.name == new_name)
+        {
+            return Err((
+                REQUEST_FAILED,
+                format!("'{}' is already declared in this file", new_name),
+            ));
+        }
+        if document
+            .definition(token)
+            .is_some_and(|symbol| symbol.import)
+        {
+            return Err((
+                REQUEST_FAILED,
+                format!(
+                    "'{}' is the name of a loaded file, rename the file instead",
+                    token.value
+                ),
+            ));
+        }
+        let edits: Vec<Value> = document
+            .variable_tokens(&token.value)
+            .iter()
+            .map(|token| {
+                json!({
+                    "range": document.range(token.line, token.start, token.end),
+                    "newText": new_name,
+                })
+            })
+            .collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use serde_json::{json, Value};
+
+    use super::{serve, METHOD_NOT_FOUND, PARSE_ERROR, REQUEST_FAILED};
+    use crate::lsp::transport::{read_message, write_message};
+
+    const URI: &str = "file:///test.red";
+    const SOURCE: &str = "Text name = \"Anna\"\nZahl a = 1\nOutput \"Hallo $name$\", a\n";
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({
+            "textDocument": { "uri": URI },
+            "position": { "line": line, "character": character },
+        })
+    }
+
+    /// Runs the server with the messages as a client would send them and returns its answers.
+    fn run(messages: &[Value]) -> (bool, Vec<Value>) {
+        let mut input = Vec::new();
+        for message in messages {
+            write_message(&mut input, message).unwrap();
+        }
+        run_input(input)
+    }
+
+    /// Runs the server with already framed input, which may contain invalid messages.
+    fn run_input(input: Vec<u8>) -> (bool, Vec<Value>) {
+        let mut output = Vec::new();
+        let shutdown = serve(Cursor::new(input), &mut output).unwrap();
+        let mut reader = Cursor::new(output);
+        let mut answers = Vec::new();
+        while let Some(answer) = read_message(&mut reader).unwrap() {
+            answers.push(answer.unwrap());
+        }
+        (shutdown, answers)
+    }
+
+    fn response(answers: &[Value], id: u64) -> &Value {
+        answers
+            .iter()
+            .find(|answer| answer["id"] == id)
+            .unwrap_or_else(|| panic!("no response for request {}", id))
+    }
+
+    #[test]
+    fn scripted_session() {
+        let mut rename_params = at(2, 16);
+        rename_params["newName"] = json!("vorname");
+        let mut collision_params = at(2, 16);
+        collision_params["newName"] = json!("a");
+        let (shutdown, answers) = run(&[
+            request(1, "initialize", json!({})),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didOpen",
+                "params": {
+                    "textDocument": { "uri": URI, "languageId": "red", "version": 1, "text": SOURCE },
+                },
+            }),
+            request(2, "textDocument/completion", at(2, 0)),
+            request(3, "textDocument/definition", at(2, 16)),
+            request(4, "textDocument/hover", at(2, 16)),
+            request(5, "textDocument/rename", rename_params),
+            request(6, "textDocument/rename", collision_params),
+            request(7, "unknown/method", json!({})),
+            request(8, "shutdown", Value::Null),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+        assert!(shutdown);
+
+        let capabilities = &response(&answers, 1)["result"]["capabilities"];
+        assert_eq!(capabilities["textDocumentSync"], 1);
+        assert_eq!(capabilities["renameProvider"], true);
+
+        let diagnostics = answers
+            .iter()
+            .find(|answer| answer["method"] == "textDocument/publishDiagnostics")
+            .unwrap();
+        assert_eq!(diagnostics["params"]["uri"], URI);
+        assert_eq!(diagnostics["params"]["diagnostics"], json!([]));
+
+        let completion = response(&answers, 2)["result"].as_array().unwrap();
+        let labels: Vec<&str> = completion
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"Output"));
+        assert!(labels.contains(&"name"));
+        assert!(labels.contains(&"a"));
+        assert!(labels.contains(&"Länge"));
+
+        assert_eq!(
+            response(&answers, 3)["result"],
+            json!({
+                "uri": URI,
+                "range": {
+                    "start": { "line": 0, "character": 5 },
+                    "end": { "line": 0, "character": 9 },
+                },
+            })
+        );
+
+        assert_eq!(
+            response(&answers, 4)["result"]["contents"]["value"],
+            "```red\nText name\n```"
+        );
+
+        let edits = response(&answers, 5)["result"]["changes"][URI]
+            .as_array()
+            .unwrap();
+        assert_eq!(edits.len(), 2);
+        assert!(edits.iter().all(|edit| edit["newText"] == "vorname"));
+        assert_eq!(
+            edits[1]["range"]["start"],
+            json!({ "line": 2, "character": 15 })
+        );
+
+        assert_eq!(response(&answers, 6)["error"]["code"], REQUEST_FAILED);
+        assert_eq!(response(&answers, 7)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(response(&answers, 8)["result"], Value::Null);
+    }
+
+    #[test]
+    fn errors_are_published() {
+        let (shutdown, answers) = run(&[json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "text": "Zahl a = \"x\"\n" } },
+        })]);
+        assert!(!shutdown);
+        let diagnostics = answers[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+    }
+
+    #[test]
+    fn invalid_json_is_answered() {
+        let mut input = b"Content-Length: 7\r\n\r\n{\"id\":}".to_vec();
+        write_message(&mut input, &request(1, "shutdown", Value::Null)).unwrap();
+        let (shutdown, answers) = run_input(input);
+        assert!(shutdown);
+        assert_eq!(answers[0]["id"], Value::Null);
+        assert_eq!(answers[0]["error"]["code"], PARSE_ERROR);
+        assert_eq!(response(&answers, 1)["result"], Value::Null);
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Longest message that is read, in bytes, so a wrong `Content-Length` does not use up the memory
+const MAX_MESSAGE_LEN: usize = 1 << 26;
+
+/// Reads the next message, which is JSON after a `Content-Length` header. Returns `None` at
+/// the end of the input. A message that is not valid JSON is an inner error, as the messages
+/// after it can still be read.
+pub fn read_message(
+    reader: &mut impl BufRead,
+) -> io::Result<Option<Result<Value, serde_json::Error>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = length else {
+        return Err(invalid_data("Missing Content-Length header"));
+    };
+    if length > MAX_MESSAGE_LEN {
+        return Err(invalid_data(&format!(
+            "Message of {} bytes is longer than the limit of {} bytes",
+            length, MAX_MESSAGE_LEN
+        )));
+    }
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    Ok(Some(serde_json::from_slice(&content)))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, ErrorKind};
+
+    use serde_json::json;
+
+    use super::{read_message, write_message};
+
+    #[test]
+    fn round_trip() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({ "id": 1 })).unwrap();
+        assert_eq!(framed, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+        let mut reader = Cursor::new(framed);
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(message.unwrap(), json!({ "id": 1 }));
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_json_keeps_the_next_message() {
+        let mut framed = b"Content-Length: 5\r\n\r\n{\"id\"".to_vec();
+        write_message(&mut framed, &json!({ "id": 2 })).unwrap();
+        let mut reader = Cursor::new(framed);
+        assert!(read_message(&mut reader).unwrap().unwrap().is_err());
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(message.unwrap(), json!({ "id": 2 }));
+    }
+
+    #[test]
+    fn too_long_message() {
+        let mut reader = Cursor::new(b"Content-Length: 99999999999\r\n\r\n{}".to_vec());
+        let err = read_message(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn missing_header() {
+        let mut reader = Cursor::new(b"Content-Type: text/json\r\n\r\n{}".to_vec());
+        let err = read_message(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_body() {
+        let mut reader = Cursor::new(b"Content-Length: 20\r\n\r\n{\"id\":1}".to_vec());
+        let err = read_message(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,19 +1,175 @@
+#![allow(clippy::upper_case_acronyms, clippy::module_inception)]
+
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::{env, fs};
+use std::{env, fs, io};
 
-use crate::interpreter::Interpreter;
+use crate::checker::TypeChecker;
+use crate::error::Stage;
+use crate::interpreter::{FileAccess, Interpreter};
 use crate::parser::Parser;
 use lexer::Lexer;
 use stopwatch::StopWatch;
+use test_runner::TestOptions;
 
+mod checker;
 mod error;
+mod formatter;
 mod interpreter;
 mod lexer;
+mod linter;
+mod lsp;
 mod parser;
 mod stopwatch;
+mod test_runner;
+
+/// Exit code for wrong arguments and files that can not be read. The codes of the interpreter
+/// lie above `MAX_EXIT_CODE`, so they can not be confused with a code from `Beenden`.
+const EXIT_USAGE: i32 = 121;
+/// Exit codes for errors found by the lexer, the parser, the type checker and while running
+const EXIT_LEXER: i32 = 122;
+const EXIT_PARSER: i32 = 123;
+const EXIT_TYPE_CHECK: i32 = 124;
+const EXIT_RUNTIME: i32 = 125;
+
+/// Command line options of the interpreter.
+struct Options {
+    command: Command,
+    /// Seed for `Zufall`, without it every run gives different numbers
+    seed: Option<u64>,
+    /// `--snapshot`: tests compare their output with a `.stdout` file
+    snapshot: bool,
+    /// `--bless`: tests write their output to the `.stdout` file instead
+    bless: bool,
+    /// `--sandbox` disables file access, `--sandbox=<dir>` limits it to a directory
+    file_access: FileAccess,
+    /// `--strict`: environment variables that are not set are an error
+    strict: bool,
+    /// Arguments after `--`, which are passed to the script
+    script_args: Vec<String>,
+    /// `--check`: fmt only shows the files that are not formatted
+    check: bool,
+}
+
+enum Command {
+    /// Runs `input.red`
+    Run,
+    /// `red test <dir>` runs every script in the directory as a test
+    Test(PathBuf),
+    /// `red fmt [files]` formats the files, without files `input.red`
+    Fmt(Vec<PathBuf>),
+    /// `red lint [files]` shows warnings for the files, without files for `input.red`
+    Lint(Vec<PathBuf>),
+    /// `red lsp` is a language server for editors, talking LSP over stdin and stdout
+    Lsp,
+}
+
+impl Command {
+    /// Files given after `fmt` or `lint`
+    fn files(&mut self) -> Option<&mut Vec<PathBuf>> {
+        match self {
+            Command::Fmt(files) | Command::Lint(files) => Some(files),
+            Command::Run | Command::Test(_) | Command::Lsp => None,
+        }
+    }
+}
+
+fn parse_options() -> Options {
+    let mut options = Options {
+        command: Command::Run,
+        seed: None,
+        snapshot: false,
+        bless: false,
+        file_access: FileAccess::All,
+        strict: false,
+        script_args: Vec::new(),
+        check: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "test" => {
+                let Some(dir) = args.next() else {
+                    println!("test expects a directory, e.g. red test tests");
+                    exit(EXIT_USAGE);
+                };
+                options.command = Command::Test(PathBuf::from(dir));
+            }
+            "fmt" => options.command = Command::Fmt(Vec::new()),
+            "lint" => options.command = Command::Lint(Vec::new()),
+            "lsp" => options.command = Command::Lsp,
+            "--check" => options.check = true,
+            "--seed" => {
+                let seed = args.next().and_then(|seed| seed.parse::<u64>().ok());
+                if seed.is_none() {
+                    println!("--seed expects a positive whole number, e.g. --seed 42");
+                    exit(EXIT_USAGE);
+                }
+                options.seed = seed;
+            }
+            "--snapshot" => options.snapshot = true,
+            "--bless" => options.bless = true,
+            "--strict" => options.strict = true,
+            "--" => options.script_args = args.by_ref().collect(),
+            "--sandbox" => options.file_access = FileAccess::None,
+            sandbox if sandbox.starts_with("--sandbox=") => {
+                let dir = PathBuf::from(&sandbox["--sandbox=".len()..]);
+                if !dir.is_dir() {
+                    println!("--sandbox expects an existing directory, e.g. --sandbox=daten");
+                    exit(EXIT_USAGE);
+                }
+                options.file_access = FileAccess::Directory(dir);
+            }
+            other => match options.command.files() {
+                Some(files) if !other.starts_with('-') => files.push(PathBuf::from(other)),
+                _ => {
+                    println!("Unknown argument '{}'", other);
+                    exit(EXIT_USAGE);
+                }
+            },
+        }
+    }
+    options
+}
+
+/// The given files, or `input.red` if there are none.
+fn files_or_input(files: &[PathBuf]) -> Vec<PathBuf> {
+    match files.is_empty() {
+        true => vec![PathBuf::from("input.red")],
+        false => files.to_vec(),
+    }
+}
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "full");
+    let options = parse_options();
+    if let Command::Test(dir) = &options.command {
+        let summary = test_runner::run_tests(
+            dir,
+            &TestOptions {
+                seed: options.seed,
+                snapshot: options.snapshot || options.bless,
+                bless: options.bless,
+                file_access: options.file_access.clone(),
+            },
+        );
+        exit(if summary.success() { 0 } else { 1 });
+    }
+    if let Command::Fmt(files) = &options.command {
+        let formatted = formatter::format_files(&files_or_input(files), options.check);
+        exit(if formatted { 0 } else { 1 });
+    }
+    if let Command::Lint(files) = &options.command {
+        let clean = linter::lint_files(&files_or_input(files));
+        exit(if clean { 0 } else { 1 });
+    }
+    if let Command::Lsp = &options.command {
+        let shutdown = lsp::serve(io::stdin().lock(), io::stdout()).unwrap_or_else(|err| {
+            eprintln!("Error in the language server: {}", err);
+            false
+        });
+        exit(if shutdown { 0 } else { 1 });
+    }
     let mut task_stopwatch = StopWatch::new(true);
     let mut overall_stopwatch = StopWatch::new(true);
     overall_stopwatch.start();
@@ -21,7 +177,7 @@ fn main() {
     task_stopwatch.start();
     let mut file_content = fs::read_to_string("input.red").unwrap_or_else(|_| {
         println!("Error while reading file 'input.red'");
-        exit(-1);
+        exit(EXIT_USAGE);
     });
     task_stopwatch.stop("Reading File");
 
@@ -31,7 +187,7 @@ fn main() {
     let mut lexer = Lexer::new(&file_content);
     let lexed = lexer.lex().unwrap_or_else(|err| {
         err.print_error(&file_content);
-        exit(-1);
+        exit(EXIT_LEXER);
     });
     //println!("{:?}", lexed.clone());
     task_stopwatch.stop("Lexing");
@@ -40,18 +196,36 @@ fn main() {
     let mut parser = Parser::new(lexed);
     let parsed = parser.parse().unwrap_or_else(|err| {
         err.print_error(&file_content);
-        exit(-1);
+        exit(EXIT_PARSER);
     });
     //println!("{:?}", parsed);
     task_stopwatch.stop("Parsing");
 
     task_stopwatch.start();
-    let mut interpreter = Interpreter::new();
-    interpreter.run(parsed).unwrap_or_else(|err| {
+    TypeChecker::new().check(&parsed).unwrap_or_else(|err| {
+        err.print_error(&file_content);
+        exit(EXIT_TYPE_CHECK);
+    });
+    task_stopwatch.stop("Type checking");
+
+    task_stopwatch.start();
+    let mut interpreter = Interpreter::new(options.seed)
+        .with_file(Path::new("input.red"))
+        .with_file_access(options.file_access)
+        .with_args(&options.script_args)
+        .with_strict_env(options.strict);
+    let exit_code = interpreter.run(parsed).unwrap_or_else(|err| {
         err.print_error(&file_content);
-        exit(-1);
+        // Errors in files loaded with `Lade` exit like the same error in the script itself
+        exit(match err.stage() {
+            Some(Stage::Lexer) => EXIT_LEXER,
+            Some(Stage::Parser) => EXIT_PARSER,
+            Some(Stage::TypeCheck) => EXIT_TYPE_CHECK,
+            None => EXIT_RUNTIME,
+        });
     });
     task_stopwatch.stop("Interpreting");
 
     overall_stopwatch.stop("Overall Execution");
+    exit(exit_code);
 }
@@ -3,12 +3,14 @@ use crate::{
     lexer::{Token, TokenType},
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct IdentifierExpression {
     pub line: usize,
     pub start: usize,
     pub end: usize,
     pub var_name: String,
+    /// Index and slice operations applied to the variable, e.g. `liste[0]`
+    pub accessors: Vec<Accessor>,
 }
 
 impl IdentifierExpression {
@@ -18,42 +20,295 @@ impl IdentifierExpression {
             start,
             end,
             var_name,
+            accessors: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum Accessor {
+    /// `liste[index]` with indices starting at 0, or `tabelle[schlüssel]`
+    Index(ValueExpression),
+    /// `liste[start:end]`, the end is exclusive and both bounds are optional
+    Slice(Option<ValueExpression>, Option<ValueExpression>),
+    /// `person.name`
+    Field(IdentifierExpression),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionCall {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub name: IdentifierExpression,
+    pub args: Vec<ValueExpression>,
+}
+
+impl FunctionCall {
+    pub fn new(
+        line: usize,
+        start: usize,
+        end: usize,
+        name: IdentifierExpression,
+        args: Vec<ValueExpression>,
+    ) -> Self {
+        FunctionCall {
+            line,
+            start,
+            end,
+            name,
+            args,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum NumberExpressionTypes {
     Value(i64),
+    /// A Kommazahl like `3.14`
+    Decimal(f64),
     Identifier(IdentifierExpression),
     Add(Box<NumberExpression>, Box<NumberExpression>),
     Sub(Box<NumberExpression>, Box<NumberExpression>),
     Mult(Box<NumberExpression>, Box<NumberExpression>),
     Div(Box<NumberExpression>, Box<NumberExpression>),
+    Call(FunctionCall),
+    /// `wert enthält teil`, which is 1 if the Liste, Tabelle or Text contains the value and 0
+    /// otherwise
+    Contains(ValueExpression, ValueExpression),
+    /// `a == b`, `a < b` and the other comparisons, which are 1 if true and 0 otherwise
+    Compare(CompareOperator, ValueExpression, ValueExpression),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CompareOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl CompareOperator {
+    pub fn from_token(token_type: &TokenType) -> Option<Self> {
+        match token_type {
+            TokenType::EQUALEQUAL => Some(CompareOperator::Equal),
+            TokenType::NOTEQUAL => Some(CompareOperator::NotEqual),
+            TokenType::LESS => Some(CompareOperator::Less),
+            TokenType::LESSEQUAL => Some(CompareOperator::LessEqual),
+            TokenType::GREATER => Some(CompareOperator::Greater),
+            TokenType::GREATEREQUAL => Some(CompareOperator::GreaterEqual),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum TextExpressionTypes {
     Concat(TextExpression, TextExpression),
     Value(String),
     Identifier(IdentifierExpression),
+    Number(NumberExpression),
+    Formatted(TextExpression, FormatSpec),
+    Call(FunctionCall),
+}
+
+/// Expression whose type is only known at runtime, used wherever any kind of value is accepted.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValueExpressionTypes {
+    Identifier(IdentifierExpression),
+    Call(FunctionCall),
+    Number(NumberExpression),
+    Text(TextExpression),
+    List(Vec<ValueExpression>),
+    /// Key and value pairs of a Tabelle literal like `{apfel: 120, birne: 90}`
+    Table(Vec<(ValueExpression, ValueExpression)>),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FormatAlign {
+    Left,
+    Right,
+    Center,
 }
-impl TextExpressionTypes {
-    pub fn is_identifier(&self) -> bool {
+
+/// Parsed format specifier of an interpolation like `$preis:.2$`, `$n:05$` or `$name:>20$`.
+/// The syntax is `[[fill]align][0][width][.precision]` with `<`, `>` or `^` as align.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<FormatAlign>,
+    pub zero_pad: bool,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+/// Largest width and precision of a format specifier, so `$n:>999999999$` is an error instead
+/// of a crash
+const MAX_FORMAT_WIDTH: usize = 1000;
+
+impl FormatSpec {
+    pub fn from_token(token: &Token) -> Result<Self, CodeError> {
+        let error = || {
+            CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Invalid format specifier. Expected [[fill]align][0][width][.precision], e.g. .2, 05 or >20",
+            )
+        };
+        let to_align = |ch: Option<&char>| match ch {
+            Some('<') => Some(FormatAlign::Left),
+            Some('>') => Some(FormatAlign::Right),
+            Some('^') => Some(FormatAlign::Center),
+            _ => None,
+        };
+
+        let chars: Vec<char> = token.value.chars().collect();
+        let mut spec = FormatSpec {
+            fill: ' ',
+            align: None,
+            zero_pad: false,
+            width: 0,
+            precision: None,
+        };
+        let mut idx = 0;
+        if let Some(align) = to_align(chars.get(1)) {
+            spec.fill = chars[0];
+            spec.align = Some(align);
+            idx = 2;
+        } else if let Some(align) = to_align(chars.first()) {
+            spec.align = Some(align);
+            idx = 1;
+        }
+        if chars.get(idx) == Some(&'0') {
+            spec.zero_pad = true;
+            idx += 1;
+        }
+        let width: String = chars[idx..]
+            .iter()
+            .take_while(|ch| ch.is_ascii_digit())
+            .collect();
+        idx += width.len();
+        if !width.is_empty() {
+            spec.width = width.parse().unwrap_or(usize::MAX);
+        }
+        if chars.get(idx) == Some(&'.') {
+            let precision: String = chars[idx + 1..]
+                .iter()
+                .take_while(|ch| ch.is_ascii_digit())
+                .collect();
+            if precision.is_empty() {
+                return Err(error());
+            }
+            idx += precision.len() + 1;
+            spec.precision = Some(precision.parse().unwrap_or(usize::MAX));
+        }
+        if idx != chars.len() {
+            return Err(error());
+        }
+        if spec.width > MAX_FORMAT_WIDTH || spec.precision.unwrap_or(0) > MAX_FORMAT_WIDTH {
+            return Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                format!(
+                    "Width and precision of a format specifier can be at most {}",
+                    MAX_FORMAT_WIDTH
+                )
+                .as_str(),
+            ));
+        }
+        Ok(spec)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum VariableType {
+    Text,
+    Number,
+    Decimal,
+    List,
+    Table,
+    /// A `Struktur` with the given name
+    Record(String),
+}
+
+impl VariableType {
+    /// Name of the type as written in red code.
+    pub fn name(&self) -> &str {
         match self {
-            TextExpressionTypes::Identifier(_) => true,
-            TextExpressionTypes::Concat(exp1, _) => exp1.get_expression().is_identifier(),
-            _ => false,
+            VariableType::Text => "Text",
+            VariableType::Number => "Zahl",
+            VariableType::Decimal => "Kommazahl",
+            VariableType::List => "Liste",
+            VariableType::Table => "Tabelle",
+            VariableType::Record(name) => name,
         }
     }
 }
 
+/// A field of a `Struktur` declaration like `Text name`
+#[derive(Debug, PartialEq)]
+pub struct FieldDefinition {
+    pub field_type: VariableType,
+    pub name: IdentifierExpression,
+}
+
+/// `Struktur Person: Text name, Zahl alter`
+#[derive(Debug, PartialEq)]
+pub struct StructDefinition {
+    pub name: IdentifierExpression,
+    pub fields: Vec<FieldDefinition>,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ExpressionTypes {
     TextAssignment(IdentifierExpression, TextExpression),
     NumberAssignment(IdentifierExpression, NumberExpression),
-    InputStatement(IdentifierExpression),
-    OutputStatement(IdentifierExpression),
+    DecimalAssignment(IdentifierExpression, NumberExpression),
+    ListAssignment(IdentifierExpression, ValueExpression),
+    ListAppend(IdentifierExpression, ValueExpression),
+    TableAssignment(IdentifierExpression, ValueExpression),
+    /// `name = value` for an existing variable, or `name[index] = value` and
+    /// `name.feld = value` for a single element of a Liste, Tabelle or Struktur
+    Assignment(IdentifierExpression, ValueExpression),
+    StructDeclaration(StructDefinition),
+    /// `Konstante` followed by a declaration, the variable can not be changed afterwards
+    ConstantDeclaration(Box<Expression>),
+    /// Name of the Struktur, the variable and its value, which defaults to empty fields
+    RecordAssignment(
+        IdentifierExpression,
+        IdentifierExpression,
+        Option<ValueExpression>,
+    ),
+    /// Removes an index of a Liste or a key of a Tabelle
+    ListRemove(IdentifierExpression, ValueExpression),
+    InputStatement(IdentifierExpression, VariableType, Option<TextExpression>),
+    /// Values to print and whether a line break follows them
+    OutputStatement(Vec<ValueExpression>, bool),
+    /// Loop variable, optional second variable for the value, iterated value and loop body
+    ForEachStatement(
+        IdentifierExpression,
+        Option<IdentifierExpression>,
+        ValueExpression,
+        Vec<Expression>,
+    ),
+    /// Block to try, variable for a caught error and the block that handles it
+    TryStatement(Vec<Expression>, IdentifierExpression, Vec<Expression>),
+    /// Raises an error with the value as message
+    ThrowStatement(ValueExpression),
+    /// Condition that must not be 0 and an optional message for when it is
+    AssertStatement(ValueExpression, Option<TextExpression>),
+    /// Path, variable for the content and whether it is read as Liste of lines
+    ReadFile(ValueExpression, IdentifierExpression, bool),
+    /// Written value, path and whether it is appended to the file
+    WriteFile(ValueExpression, ValueExpression, bool),
+    /// Stops the script with the exit code, which is 0 if it is missing
+    ExitStatement(Option<NumberExpression>),
+    /// Namespace named after the file and the path of the loaded file
+    Import(IdentifierExpression, String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -77,7 +332,7 @@ impl Expression {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct TextExpression {
     pub line: usize,
     pub start: usize,
@@ -120,6 +375,26 @@ impl TextExpression {
             )),
         }
     }
+    pub fn from_number(n_expr: NumberExpression) -> Self {
+        let (line, start, end) = (n_expr.line, n_expr.start, n_expr.end);
+        match *n_expr.expression {
+            NumberExpressionTypes::Identifier(identifier) => TextExpression::new(
+                line,
+                start,
+                end,
+                TextExpressionTypes::Identifier(identifier),
+            ),
+            NumberExpressionTypes::Call(call) => {
+                TextExpression::new(line, start, end, TextExpressionTypes::Call(call))
+            }
+            expression => TextExpression::new(
+                line,
+                start,
+                end,
+                TextExpressionTypes::Number(NumberExpression::new(line, start, end, expression)),
+            ),
+        }
+    }
     pub fn join(text_exp1: TextExpression, text_exp2: TextExpression) -> Self {
         TextExpression::new(
             text_exp1.line,
@@ -133,7 +408,7 @@ impl TextExpression {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct NumberExpression {
     pub line: usize,
     pub start: usize,
@@ -160,6 +435,15 @@ impl NumberExpression {
                         end: token.end,
                         expression: Box::new(NumberExpressionTypes::Value(i64_val)),
                     })
+                } else if let (true, Ok(f64_val)) =
+                    (token.value.contains('.'), token.value.parse::<f64>())
+                {
+                    Ok(Self {
+                        line: token.line,
+                        start: token.start,
+                        end: token.end,
+                        expression: Box::new(NumberExpressionTypes::Decimal(f64_val)),
+                    })
                 } else {
                     Err(CodeError::new(
                         token.line,
@@ -186,7 +470,7 @@ impl NumberExpression {
                 token.line,
                 token.start,
                 token.end,
-                "Error while parsing value!",
+                "Expected a value (number, variable or text in quotes)",
             )),
         }
     }
@@ -194,3 +478,101 @@ impl NumberExpression {
         self.expression.as_ref()
     }
 }
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValueExpression {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    expression: Box<ValueExpressionTypes>,
+}
+
+impl ValueExpression {
+    pub fn new(line: usize, start: usize, end: usize, expression: ValueExpressionTypes) -> Self {
+        ValueExpression {
+            line,
+            start,
+            end,
+            expression: Box::new(expression),
+        }
+    }
+    /// A lone variable or function call keeps the type of its value instead of becoming a Zahl.
+    pub fn from_number(n_expr: NumberExpression) -> Self {
+        let (line, start, end) = (n_expr.line, n_expr.start, n_expr.end);
+        let expression = match *n_expr.expression {
+            NumberExpressionTypes::Identifier(identifier) => {
+                ValueExpressionTypes::Identifier(identifier)
+            }
+            NumberExpressionTypes::Call(call) => ValueExpressionTypes::Call(call),
+            expression => {
+                ValueExpressionTypes::Number(NumberExpression::new(line, start, end, expression))
+            }
+        };
+        ValueExpression::new(line, start, end, expression)
+    }
+    pub fn into_text(self) -> Result<TextExpression, CodeError> {
+        let (line, start, end) = (self.line, self.start, self.end);
+        match *self.expression {
+            ValueExpressionTypes::Identifier(identifier) => Ok(TextExpression::new(
+                line,
+                start,
+                end,
+                TextExpressionTypes::Identifier(identifier),
+            )),
+            ValueExpressionTypes::Call(call) => Ok(TextExpression::new(
+                line,
+                start,
+                end,
+                TextExpressionTypes::Call(call),
+            )),
+            ValueExpressionTypes::Number(n_expr) => Ok(TextExpression::from_number(n_expr)),
+            ValueExpressionTypes::Text(t_expr) => Ok(t_expr),
+            ValueExpressionTypes::List(_) => Err(CodeError::new(
+                line,
+                start,
+                end,
+                "Expected a text but found a Liste",
+            )),
+            ValueExpressionTypes::Table(_) => Err(CodeError::new(
+                line,
+                start,
+                end,
+                "Expected a text but found a Tabelle",
+            )),
+        }
+    }
+    pub fn into_number(self) -> Result<NumberExpression, CodeError> {
+        let (line, start, end) = (self.line, self.start, self.end);
+        let found = match *self.expression {
+            ValueExpressionTypes::Identifier(identifier) => {
+                return Ok(NumberExpression::new(
+                    line,
+                    start,
+                    end,
+                    NumberExpressionTypes::Identifier(identifier),
+                ))
+            }
+            ValueExpressionTypes::Call(call) => {
+                return Ok(NumberExpression::new(
+                    line,
+                    start,
+                    end,
+                    NumberExpressionTypes::Call(call),
+                ))
+            }
+            ValueExpressionTypes::Number(n_expr) => return Ok(n_expr),
+            ValueExpressionTypes::Text(_) => "text",
+            ValueExpressionTypes::List(_) => "Liste",
+            ValueExpressionTypes::Table(_) => "Tabelle",
+        };
+        Err(CodeError::new(
+            line,
+            start,
+            end,
+            format!("Expected a number but found a {}", found).as_str(),
+        ))
+    }
+    pub fn get_expression(&self) -> &ValueExpressionTypes {
+        self.expression.as_ref()
+    }
+}
@@ -1,11 +1,14 @@
+use std::path::Path;
+
 use crate::{
     error::CodeError,
     lexer::{Token, TokenType},
 };
 
 use super::{
-    Expression, ExpressionTypes, IdentifierExpression, NumberExpression, NumberExpressionTypes,
-    TextExpression, TextExpressionTypes,
+    Accessor, CompareOperator, Expression, ExpressionTypes, FieldDefinition, FormatSpec,
+    FunctionCall, IdentifierExpression, NumberExpression, NumberExpressionTypes, StructDefinition,
+    TextExpression, TextExpressionTypes, ValueExpression, ValueExpressionTypes, VariableType,
 };
 
 pub struct Parser {
@@ -16,7 +19,10 @@ pub struct Parser {
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
         Parser {
-            tokens,
+            tokens: tokens
+                .into_iter()
+                .filter(|token| token.token_type != TokenType::COMMENT)
+                .collect(),
             token_idx: 0,
         }
     }
@@ -29,7 +35,7 @@ impl Parser {
         if let Some(token) = self.tokens.get(self.token_idx) {
             Ok(token)
         } else {
-            if self.tokens.len() != 0 {
+            if !self.tokens.is_empty() {
                 let last_token = self.tokens.last().unwrap();
                 Err(CodeError::new(
                     last_token.line,
@@ -44,6 +50,30 @@ impl Parser {
     }
 
     pub fn parse(&mut self) -> Result<Vec<Expression>, CodeError> {
+        let expressions = self.parse_block()?;
+        let token = self.get_current_token()?;
+        if token.token_type == TokenType::END {
+            return Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Found Ende without a block to close",
+            ));
+        }
+        if token.token_type == TokenType::CATCH {
+            return Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Found Fehler without Versuche",
+            ));
+        }
+        Ok(expressions)
+    }
+
+    /// Parses instructions until the end of the file, an `Ende` or a `Fehler`, which is not
+    /// consumed.
+    fn parse_block(&mut self) -> Result<Vec<Expression>, CodeError> {
         let mut expressions = Vec::new();
         while self.token_idx < self.tokens.len() {
             let token = self.get_current_token()?;
@@ -52,7 +82,7 @@ impl Parser {
                     self.next_token();
                     continue;
                 }
-                TokenType::EOF => {
+                TokenType::EOF | TokenType::END | TokenType::CATCH => {
                     break;
                 }
                 _ => {
@@ -71,6 +101,7 @@ impl Parser {
         match token.token_type {
             TokenType::TEXT => {
                 let (identifier, value) = self.try_get_string_assignment()?;
+                self.expect_line_end()?;
                 Ok(Expression::new(
                     line,
                     token_start,
@@ -80,6 +111,7 @@ impl Parser {
             }
             TokenType::ZAHL => {
                 let (identifier, value) = self.try_get_number_assignment()?;
+                self.expect_line_end()?;
                 Ok(Expression::new(
                     line,
                     token_start,
@@ -87,24 +119,472 @@ impl Parser {
                     ExpressionTypes::NumberAssignment(identifier, value),
                 ))
             }
-            TokenType::OUTPUT => {
+            TokenType::KOMMAZAHL => {
+                let (identifier, value) = self.try_get_number_assignment()?;
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::DecimalAssignment(identifier, value),
+                ))
+            }
+            TokenType::OUTPUT | TokenType::OUTPUTNONEWLINE => {
+                let new_line = token.token_type == TokenType::OUTPUT;
                 self.next_token();
-                let identifier = self.try_get_identifier()?;
+                let mut values = vec![self.try_get_value()?];
+                while self.get_current_token()?.token_type == TokenType::COMMA {
+                    self.next_token();
+                    values.push(self.try_get_value()?);
+                }
+                self.expect_line_end()?;
                 Ok(Expression::new(
                     line,
                     token_start,
-                    identifier.end,
-                    ExpressionTypes::OutputStatement(identifier),
+                    values.last().unwrap().end,
+                    ExpressionTypes::OutputStatement(values, new_line),
                 ))
             }
             TokenType::INPUT => {
+                self.next_token();
+                let var_type = match self.get_current_token()?.token_type {
+                    TokenType::ZAHL => {
+                        self.next_token();
+                        VariableType::Number
+                    }
+                    TokenType::KOMMAZAHL => {
+                        self.next_token();
+                        VariableType::Decimal
+                    }
+                    TokenType::TEXT => {
+                        self.next_token();
+                        VariableType::Text
+                    }
+                    _ => VariableType::Text,
+                };
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let token = self.get_current_token()?;
+                let prompt = match token.token_type {
+                    TokenType::QUOTE => Some(self.try_get_text_expression()?),
+                    TokenType::NEWLINE | TokenType::EOF => None,
+                    _ => {
+                        return Err(CodeError::new(
+                            token.line,
+                            token.start,
+                            token.end,
+                            "Expected prompt text in quotes",
+                        ))
+                    }
+                };
+                self.expect_line_end()?;
+                let end = prompt.as_ref().map_or(identifier.end, |prompt| prompt.end);
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    end,
+                    ExpressionTypes::InputStatement(identifier, var_type, prompt),
+                ))
+            }
+            TokenType::LISTE => {
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let value = if self.is_assignment()? {
+                    self.next_token();
+                    self.try_get_value()?
+                } else {
+                    ValueExpression::new(
+                        line,
+                        token_start,
+                        identifier.end,
+                        ValueExpressionTypes::List(Vec::new()),
+                    )
+                };
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::ListAssignment(identifier, value),
+                ))
+            }
+            TokenType::TABELLE => {
                 self.next_token();
                 let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let value = if self.is_assignment()? {
+                    self.next_token();
+                    self.try_get_value()?
+                } else {
+                    ValueExpression::new(
+                        line,
+                        token_start,
+                        identifier.end,
+                        ValueExpressionTypes::Table(Vec::new()),
+                    )
+                };
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::TableAssignment(identifier, value),
+                ))
+            }
+            TokenType::CONST => {
+                let token_end = token.end;
+                self.next_token();
+                let has_value = self.tokens[self.token_idx..]
+                    .iter()
+                    .take_while(|token| {
+                        !matches!(token.token_type, TokenType::NEWLINE | TokenType::EOF)
+                    })
+                    .any(|token| token.token_type == TokenType::EQUAL);
+                let declaration = self.parser_instruction()?;
+                let is_declaration = matches!(
+                    declaration.get_expression(),
+                    ExpressionTypes::TextAssignment(..)
+                        | ExpressionTypes::NumberAssignment(..)
+                        | ExpressionTypes::DecimalAssignment(..)
+                        | ExpressionTypes::ListAssignment(..)
+                        | ExpressionTypes::TableAssignment(..)
+                        | ExpressionTypes::RecordAssignment(..)
+                );
+                if !is_declaration || !has_value {
+                    return Err(CodeError::new(
+                        line,
+                        token_start,
+                        token_end,
+                        "Konstante must be followed by a declaration with a value, e.g. Konstante Zahl MAX = 10",
+                    ));
+                }
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    declaration.end,
+                    ExpressionTypes::ConstantDeclaration(Box::new(declaration)),
+                ))
+            }
+            TokenType::STRUKTUR => {
+                self.next_token();
+                let name = self.try_get_identifier()?;
+                self.next_token();
+                self.expect_token(
+                    TokenType::COLON,
+                    "Expected : after the name of the Struktur",
+                )?;
+                let mut fields = Vec::new();
+                loop {
+                    let type_token = self.get_current_token()?;
+                    let field_type = match type_token.token_type {
+                        TokenType::TEXT => VariableType::Text,
+                        TokenType::ZAHL => VariableType::Number,
+                        TokenType::KOMMAZAHL => VariableType::Decimal,
+                        TokenType::LISTE => VariableType::List,
+                        TokenType::TABELLE => VariableType::Table,
+                        _ => VariableType::Record(type_token.value.to_owned()),
+                    };
+                    self.next_token();
+                    let field_name = self.try_get_identifier()?;
+                    self.next_token();
+                    fields.push(FieldDefinition {
+                        field_type,
+                        name: field_name,
+                    });
+                    if self.get_current_token()?.token_type != TokenType::COMMA {
+                        break;
+                    }
+                    self.next_token();
+                }
+                self.expect_line_end()?;
+                let end = fields.last().unwrap().name.end;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    end,
+                    ExpressionTypes::StructDeclaration(StructDefinition { name, fields }),
+                ))
+            }
+            TokenType::TYPENAME => {
+                let type_name = IdentifierExpression::new(
+                    token.line,
+                    token.start,
+                    token.end,
+                    token.value.to_owned(),
+                );
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let value = if self.is_assignment()? {
+                    self.next_token();
+                    Some(self.try_get_value()?)
+                } else {
+                    None
+                };
+                self.expect_line_end()?;
+                let end = value.as_ref().map_or(identifier.end, |value| value.end);
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    end,
+                    ExpressionTypes::RecordAssignment(type_name, identifier, value),
+                ))
+            }
+            TokenType::IDENTIFIER => {
+                let identifier = self.try_get_variable()?;
+                let operator = self.get_current_token()?.token_type.clone();
+                self.next_token();
+                let value = self.try_get_value()?;
+                let value = if operator == TokenType::EQUAL {
+                    value
+                } else {
+                    // `x += 1` is the same as `x = x + 1`
+                    let current = NumberExpression::new(
+                        identifier.line,
+                        identifier.start,
+                        identifier.end,
+                        NumberExpressionTypes::Identifier(identifier.clone()),
+                    );
+                    let rhs = value.into_number()?;
+                    let (start, end) = (identifier.start, rhs.end);
+                    let rhs = Box::new(rhs);
+                    let operation = match operator {
+                        TokenType::ADDEQUAL => NumberExpressionTypes::Add(Box::new(current), rhs),
+                        TokenType::SUBEQUAL => NumberExpressionTypes::Sub(Box::new(current), rhs),
+                        TokenType::MULTEQUAL => NumberExpressionTypes::Mult(Box::new(current), rhs),
+                        _ => NumberExpressionTypes::Div(Box::new(current), rhs),
+                    };
+                    ValueExpression::new(
+                        line,
+                        start,
+                        end,
+                        ValueExpressionTypes::Number(NumberExpression::new(
+                            line, start, end, operation,
+                        )),
+                    )
+                };
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::Assignment(identifier, value),
+                ))
+            }
+            TokenType::APPEND => {
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let value = self.try_get_value()?;
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::ListAppend(identifier, value),
+                ))
+            }
+            TokenType::REMOVE => {
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let index = self.try_get_value()?;
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    index.end,
+                    ExpressionTypes::ListRemove(identifier, index),
+                ))
+            }
+            TokenType::FOR => {
+                let token_end = token.end;
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                let value_identifier = if self.get_current_token()?.token_type == TokenType::COMMA {
+                    self.next_token();
+                    let value_identifier = self.try_get_identifier()?;
+                    self.next_token();
+                    Some(value_identifier)
+                } else {
+                    None
+                };
+                self.next_token();
+                let iterable = self.try_get_value()?;
+                self.expect_line_end()?;
+                let body = self.parse_block()?;
+                if self.get_current_token()?.token_type != TokenType::END {
+                    return Err(CodeError::new(
+                        line,
+                        token_start,
+                        token_end,
+                        "Missing Ende for this loop",
+                    ));
+                }
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    iterable.end,
+                    ExpressionTypes::ForEachStatement(identifier, value_identifier, iterable, body),
+                ))
+            }
+            TokenType::TRY => {
+                let token_end = token.end;
+                self.next_token();
+                self.expect_line_end()?;
+                let body = self.parse_block()?;
+                let catch_token = self.get_current_token()?;
+                if catch_token.token_type != TokenType::CATCH {
+                    return Err(CodeError::new(
+                        line,
+                        token_start,
+                        token_end,
+                        "Missing Fehler for this Versuche",
+                    ));
+                }
+                self.next_token();
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                self.expect_line_end()?;
+                let handler = self.parse_block()?;
+                if self.get_current_token()?.token_type != TokenType::END {
+                    return Err(CodeError::new(
+                        line,
+                        token_start,
+                        token_end,
+                        "Missing Ende for this Versuche",
+                    ));
+                }
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    token_end,
+                    ExpressionTypes::TryStatement(body, identifier, handler),
+                ))
+            }
+            TokenType::ASSERT => {
+                self.next_token();
+                let condition = self.try_get_value()?;
+                let message = if self.get_current_token()?.token_type == TokenType::QUOTE {
+                    Some(self.try_get_value()?.into_text()?)
+                } else {
+                    None
+                };
+                self.expect_line_end()?;
+                let end = message
+                    .as_ref()
+                    .map_or(condition.end, |message| message.end);
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    end,
+                    ExpressionTypes::AssertStatement(condition, message),
+                ))
+            }
+            TokenType::READ => {
+                self.next_token();
+                let mode = self.get_current_token()?;
+                let lines = match mode.value.as_str() {
+                    "datei" => false,
+                    "zeilen" => true,
+                    _ => {
+                        return Err(CodeError::new(
+                            mode.line,
+                            mode.start,
+                            mode.end,
+                            "Expected 'datei' or 'zeilen' after Lese",
+                        ))
+                    }
+                };
+                self.next_token();
+                let path = self.try_get_value()?;
+                self.expect_in()?;
+                let identifier = self.try_get_identifier()?;
+                self.next_token();
+                self.expect_line_end()?;
                 Ok(Expression::new(
                     line,
                     token_start,
                     identifier.end,
-                    ExpressionTypes::InputStatement(identifier),
+                    ExpressionTypes::ReadFile(path, identifier, lines),
+                ))
+            }
+            TokenType::WRITE | TokenType::APPENDFILE => {
+                let append = token.token_type == TokenType::APPENDFILE;
+                self.next_token();
+                let value = self.try_get_value()?;
+                self.expect_in()?;
+                let path = self.try_get_value()?;
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    path.end,
+                    ExpressionTypes::WriteFile(value, path, append),
+                ))
+            }
+            TokenType::IMPORT => {
+                self.next_token();
+                let path = self.try_get_value()?;
+                let ValueExpressionTypes::Text(text) = path.get_expression() else {
+                    return Err(import_path_error(&path));
+                };
+                let TextExpressionTypes::Value(file) = text.get_expression() else {
+                    return Err(import_path_error(&path));
+                };
+                self.expect_line_end()?;
+                // The namespace is the file name without directory and extension
+                let name = Path::new(file)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                if name.is_empty() || !name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') {
+                    return Err(CodeError::new(
+                        path.line,
+                        path.start,
+                        path.end,
+                        format!(
+                            "'{}' can not be used as name, file names may only contain letters, digits and _",
+                            name
+                        )
+                        .as_str(),
+                    ));
+                }
+                let namespace = IdentifierExpression::new(path.line, path.start, path.end, name);
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    path.end,
+                    ExpressionTypes::Import(namespace, file.to_owned()),
+                ))
+            }
+            TokenType::EXIT => {
+                let token_end = token.end;
+                self.next_token();
+                let code = match self.get_current_token()?.token_type {
+                    TokenType::NEWLINE | TokenType::EOF => None,
+                    _ => Some(self.try_get_value()?.into_number()?),
+                };
+                self.expect_line_end()?;
+                let end = code.as_ref().map_or(token_end, |code| code.end);
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    end,
+                    ExpressionTypes::ExitStatement(code),
+                ))
+            }
+            TokenType::THROW => {
+                self.next_token();
+                let value = self.try_get_value()?;
+                self.expect_line_end()?;
+                Ok(Expression::new(
+                    line,
+                    token_start,
+                    value.end,
+                    ExpressionTypes::ThrowStatement(value),
                 ))
             }
             _ => Err(CodeError::new(
@@ -130,7 +610,11 @@ impl Parser {
         self.next_token();
         if self.is_assignment()? {
             self.next_token();
-            let value_exp = self.try_get_string_value()?;
+            let value_exp = if self.get_current_token()?.token_type == TokenType::QUOTE {
+                self.try_get_text_expression()?
+            } else {
+                self.try_get_string_value()?
+            };
 
             Ok((identifier, value_exp))
         } else {
@@ -160,7 +644,7 @@ impl Parser {
 
         if self.is_assignment()? {
             self.next_token();
-            let value_exp = self.try_get_number_value()?;
+            let value_exp = self.try_get_value()?.into_number()?;
 
             Ok((identifier, value_exp))
         } else {
@@ -212,11 +696,39 @@ impl Parser {
         let mut exp_concat = Vec::new();
 
         while match self.get_current_token()?.token_type {
-            TokenType::VALUE | TokenType::IDENTIFIER => {
+            TokenType::VALUE => {
                 exp_concat.push(TextExpression::from_token(self.get_current_token()?)?);
                 self.next_token();
                 self.token_idx < self.tokens.len()
             }
+            TokenType::IDENTIFIER => {
+                let identifier = self.try_get_variable()?;
+                let identifier = TextExpression::new(
+                    identifier.line,
+                    identifier.start,
+                    identifier.end,
+                    TextExpressionTypes::Identifier(identifier),
+                );
+                exp_concat.push(self.try_get_format(identifier)?);
+                self.token_idx < self.tokens.len()
+            }
+            TokenType::LBRACE => {
+                self.next_token();
+                let inner_exp = self.try_get_text_expression()?;
+                let formatted_exp = self.try_get_format(inner_exp)?;
+                let token = self.get_current_token()?;
+                if token.token_type != TokenType::RBRACE {
+                    return Err(CodeError::new(
+                        token.line,
+                        token.start,
+                        token.end,
+                        "Expected closing }$ for expression",
+                    ));
+                }
+                exp_concat.push(formatted_exp);
+                self.next_token();
+                self.token_idx < self.tokens.len()
+            }
             _ => false,
         } {}
 
@@ -239,127 +751,328 @@ impl Parser {
         }
     }
 
-    fn is_math_operation(&mut self) -> Result<bool, CodeError> {
-        match self.get_current_token()?.token_type {
-            TokenType::ADD | TokenType::SUB | TokenType::MULT | TokenType::DIV => Ok(true),
-            _ => Ok(false),
+    /// Wraps `text_exp` in a `Formatted` expression if a format specifier follows.
+    fn try_get_format(&mut self, text_exp: TextExpression) -> Result<TextExpression, CodeError> {
+        let token = self.get_current_token()?;
+        if token.token_type != TokenType::FORMAT {
+            return Ok(text_exp);
         }
+        let format = FormatSpec::from_token(token)?;
+        self.next_token();
+        Ok(TextExpression::new(
+            text_exp.line,
+            text_exp.start,
+            text_exp.end,
+            TextExpressionTypes::Formatted(text_exp, format),
+        ))
     }
 
-    fn try_get_math_operation(
-        &mut self,
-        prev_val: NumberExpression,
-    ) -> Result<NumberExpression, CodeError> {
-        match self.get_current_token()?.token_type {
-            TokenType::ADD => {
-                self.next_token();
-                let val1 = NumberExpression::from_token(self.get_current_token()?)?;
-                self.next_token();
-                if self.is_math_operation()? {
-                    let following_operation = self.try_get_math_operation(val1)?;
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        following_operation.end,
-                        NumberExpressionTypes::Add(
-                            Box::new(prev_val),
-                            Box::new(following_operation),
-                        ),
-                    ))
-                } else {
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Add(Box::new(prev_val), Box::new(val1)),
-                    ))
+    /// Parses quoted texts and number expressions joined with `&`.
+    fn try_get_text_expression(&mut self) -> Result<TextExpression, CodeError> {
+        self.try_get_value()?.into_text()
+    }
+
+    /// Parses a value of any type: a list, a table, a quoted text, a number expression, several
+    /// of them joined with `&` or a membership test with `enthält`.
+    fn try_get_value(&mut self) -> Result<ValueExpression, CodeError> {
+        let value_exp = self.try_get_concat()?;
+        let token_type = self.get_current_token()?.token_type.clone();
+        let operator = CompareOperator::from_token(&token_type);
+        if token_type != TokenType::CONTAINS && operator.is_none() {
+            return Ok(value_exp);
+        }
+        self.next_token();
+        let rhs = self.try_get_concat()?;
+        let (line, start, end) = (value_exp.line, value_exp.start, rhs.end);
+        let expression = match operator {
+            Some(operator) => NumberExpressionTypes::Compare(operator, value_exp, rhs),
+            None => NumberExpressionTypes::Contains(value_exp, rhs),
+        };
+        Ok(ValueExpression::from_number(NumberExpression::new(
+            line, start, end, expression,
+        )))
+    }
+
+    fn try_get_concat(&mut self) -> Result<ValueExpression, CodeError> {
+        let value_exp = self.try_get_value_operand()?;
+        if self.get_current_token()?.token_type != TokenType::CONCAT {
+            return Ok(value_exp);
+        }
+        let mut text_exp = value_exp.into_text()?;
+        while self.get_current_token()?.token_type == TokenType::CONCAT {
+            self.next_token();
+            let next_exp = self.try_get_value_operand()?.into_text()?;
+            text_exp = TextExpression::join(text_exp, next_exp);
+        }
+        Ok(ValueExpression::new(
+            text_exp.line,
+            text_exp.start,
+            text_exp.end,
+            ValueExpressionTypes::Text(text_exp),
+        ))
+    }
+
+    fn try_get_value_operand(&mut self) -> Result<ValueExpression, CodeError> {
+        let token = self.get_current_token()?;
+        let (line, start) = (token.line, token.start);
+        match token.token_type {
+            TokenType::QUOTE => {
+                self.next_token();
+                let text_exp = self.try_get_string_value()?;
+                let token = self.get_current_token()?;
+                if token.token_type != TokenType::QUOTE {
+                    return Err(CodeError::new(
+                        token.line,
+                        token.start,
+                        token.end,
+                        "Expected closing \" for text",
+                    ));
                 }
+                let end = token.end;
+                self.next_token();
+                Ok(ValueExpression::new(
+                    line,
+                    start,
+                    end,
+                    ValueExpressionTypes::Text(text_exp),
+                ))
             }
-            TokenType::SUB => {
+            TokenType::LBRACKET => {
                 self.next_token();
-                let val1 = NumberExpression::from_token(self.get_current_token()?)?;
-                self.next_token();
-                if self.is_math_operation()? {
-                    let following_operation = self.try_get_math_operation(val1)?;
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        following_operation.end,
-                        NumberExpressionTypes::Sub(
-                            Box::new(prev_val),
-                            Box::new(following_operation),
-                        ),
-                    ))
-                } else {
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Sub(Box::new(prev_val), Box::new(val1)),
-                    ))
+                let mut items = Vec::new();
+                if self.get_current_token()?.token_type != TokenType::RBRACKET {
+                    items.push(self.try_get_value()?);
+                    while self.get_current_token()?.token_type == TokenType::COMMA {
+                        self.next_token();
+                        items.push(self.try_get_value()?);
+                    }
                 }
+                let end = self.expect_token(TokenType::RBRACKET, "Expected , or ] in list")?;
+                Ok(ValueExpression::new(
+                    line,
+                    start,
+                    end,
+                    ValueExpressionTypes::List(items),
+                ))
             }
-            TokenType::MULT => {
+            TokenType::LBRACE => {
                 self.next_token();
-                let val1 = NumberExpression::from_token(self.get_current_token()?)?;
-                self.next_token();
-                if self.is_math_operation()? {
-                    let operation = NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Mult(Box::new(prev_val), Box::new(val1)),
-                    );
-                    Ok(self.try_get_math_operation(operation)?)
-                } else {
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Mult(Box::new(prev_val), Box::new(val1)),
-                    ))
+                let mut entries = Vec::new();
+                while self.get_current_token()?.token_type != TokenType::RBRACE {
+                    if !entries.is_empty() {
+                        self.expect_token(TokenType::COMMA, "Expected , or } in table")?;
+                    }
+                    let key = self.try_get_concat()?;
+                    self.expect_token(TokenType::COLON, "Expected : after key")?;
+                    entries.push((key, self.try_get_value()?));
                 }
+                let end = self.expect_token(TokenType::RBRACE, "Expected , or } in table")?;
+                Ok(ValueExpression::new(
+                    line,
+                    start,
+                    end,
+                    ValueExpressionTypes::Table(entries),
+                ))
+            }
+            _ => Ok(ValueExpression::from_number(self.try_get_number_value()?)),
+        }
+    }
+
+    /// Checks that the current token has the given type, consumes it and returns its end.
+    fn expect_token(&mut self, token_type: TokenType, err_msg: &str) -> Result<usize, CodeError> {
+        let token = self.get_current_token()?;
+        if token.token_type != token_type {
+            return Err(CodeError::new(token.line, token.start, token.end, err_msg));
+        }
+        let end = token.end;
+        self.next_token();
+        Ok(end)
+    }
+
+    /// Consumes the `in` between the value and the target of a file statement.
+    fn expect_in(&mut self) -> Result<(), CodeError> {
+        let token = self.get_current_token()?;
+        if token.token_type != TokenType::IDENTIFIER || token.value != "in" {
+            return Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Expected 'in'",
+            ));
+        }
+        self.next_token();
+        Ok(())
+    }
+
+    fn expect_line_end(&self) -> Result<(), CodeError> {
+        let token = self.get_current_token()?;
+        match token.token_type {
+            TokenType::NEWLINE | TokenType::EOF => Ok(()),
+            _ => Err(CodeError::new(
+                token.line,
+                token.start,
+                token.end,
+                "Unexpected value, expected end of line",
+            )),
+        }
+    }
+
+    /// Parses sums and differences of products, which are evaluated from left to right.
+    fn try_get_number_value(&mut self) -> Result<NumberExpression, CodeError> {
+        let mut n_expr = self.try_get_product()?;
+        loop {
+            let token_type = self.get_current_token()?.token_type.clone();
+            if !matches!(token_type, TokenType::ADD | TokenType::SUB) {
+                return Ok(n_expr);
+            }
+            self.next_token();
+            let rhs = self.try_get_product()?;
+            let (line, start, end) = (n_expr.line, n_expr.start, rhs.end);
+            let operation = if token_type == TokenType::ADD {
+                NumberExpressionTypes::Add(Box::new(n_expr), Box::new(rhs))
+            } else {
+                NumberExpressionTypes::Sub(Box::new(n_expr), Box::new(rhs))
+            };
+            n_expr = NumberExpression::new(line, start, end, operation);
+        }
+    }
+
+    fn try_get_product(&mut self) -> Result<NumberExpression, CodeError> {
+        let mut n_expr = self.try_get_number_operand()?;
+        loop {
+            let token_type = self.get_current_token()?.token_type.clone();
+            if !matches!(token_type, TokenType::MULT | TokenType::DIV) {
+                return Ok(n_expr);
             }
-            TokenType::DIV => {
+            self.next_token();
+            let rhs = self.try_get_number_operand()?;
+            let (line, start, end) = (n_expr.line, n_expr.start, rhs.end);
+            let operation = if token_type == TokenType::MULT {
+                NumberExpressionTypes::Mult(Box::new(n_expr), Box::new(rhs))
+            } else {
+                NumberExpressionTypes::Div(Box::new(n_expr), Box::new(rhs))
+            };
+            n_expr = NumberExpression::new(line, start, end, operation);
+        }
+    }
+
+    fn try_get_number_operand(&mut self) -> Result<NumberExpression, CodeError> {
+        let token = self.get_current_token()?;
+        let (line, start) = (token.line, token.start);
+        match token.token_type {
+            TokenType::LPAREN => {
                 self.next_token();
-                let val1 = NumberExpression::from_token(self.get_current_token()?)?;
+                let mut n_expr = self.try_get_number_value()?;
+                n_expr.end = self.expect_token(TokenType::RPAREN, "Expected closing )")?;
+                n_expr.start = start;
+                Ok(n_expr)
+            }
+            TokenType::IDENTIFIER => {
+                let identifier = self.try_get_variable()?;
+                Ok(NumberExpression::new(
+                    line,
+                    start,
+                    identifier.end,
+                    NumberExpressionTypes::Identifier(identifier),
+                ))
+            }
+            TokenType::SUB => {
+                // `-x` is calculated as `0 - x`
                 self.next_token();
-                if self.is_math_operation()? {
-                    let operation = NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Div(Box::new(prev_val), Box::new(val1)),
-                    );
-                    Ok(self.try_get_math_operation(operation)?)
-                } else {
-                    Ok(NumberExpression::new(
-                        prev_val.line,
-                        prev_val.start,
-                        val1.end,
-                        NumberExpressionTypes::Div(Box::new(prev_val), Box::new(val1)),
-                    ))
-                }
+                let operand = self.try_get_number_operand()?;
+                let end = operand.end;
+                let zero =
+                    NumberExpression::new(line, start, start, NumberExpressionTypes::Value(0));
+                Ok(NumberExpression::new(
+                    line,
+                    start,
+                    end,
+                    NumberExpressionTypes::Sub(Box::new(zero), Box::new(operand)),
+                ))
             }
-            _ => {
-                let current_token = self.get_current_token()?;
-                Err(CodeError::new(
-                    current_token.line,
-                    current_token.start,
-                    current_token.end,
-                    "Expected Math Operation",
+            TokenType::FUNCTION => {
+                let call = self.try_get_function_call()?;
+                Ok(NumberExpression::new(
+                    line,
+                    start,
+                    call.end,
+                    NumberExpressionTypes::Call(call),
                 ))
             }
+            _ => {
+                let n_expr = NumberExpression::from_token(token)?;
+                self.next_token();
+                Ok(n_expr)
+            }
         }
     }
 
-    fn try_get_number_value(&mut self) -> Result<NumberExpression, CodeError> {
-        let val1 = NumberExpression::from_token(self.get_current_token()?)?;
+    /// Parses a variable name followed by any number of `[index]`, `[start:end]` or `.feld`
+    /// accessors.
+    fn try_get_variable(&mut self) -> Result<IdentifierExpression, CodeError> {
+        let mut identifier = self.try_get_identifier()?;
         self.next_token();
-        if self.is_math_operation()? {
-            Ok(self.try_get_math_operation(val1)?)
-        } else {
-            Ok(val1)
+        loop {
+            match self.get_current_token()?.token_type {
+                TokenType::DOT => {
+                    self.next_token();
+                    let field = self.try_get_identifier()?;
+                    self.next_token();
+                    identifier.end = field.end;
+                    identifier.accessors.push(Accessor::Field(field));
+                    continue;
+                }
+                TokenType::LBRACKET => {}
+                _ => break,
+            }
+            self.next_token();
+            let from = match self.get_current_token()?.token_type {
+                TokenType::COLON => None,
+                _ => Some(self.try_get_value()?),
+            };
+            let is_slice = self.get_current_token()?.token_type == TokenType::COLON;
+            let accessor = match (from, is_slice) {
+                (Some(index), false) => Accessor::Index(index),
+                (from, _) => {
+                    self.next_token();
+                    let to = match self.get_current_token()?.token_type {
+                        TokenType::RBRACKET => None,
+                        _ => Some(self.try_get_value()?),
+                    };
+                    Accessor::Slice(from, to)
+                }
+            };
+            identifier.end = self.expect_token(TokenType::RBRACKET, "Expected closing ]")?;
+            identifier.accessors.push(accessor);
         }
+        Ok(identifier)
     }
+
+    fn try_get_function_call(&mut self) -> Result<FunctionCall, CodeError> {
+        let token = self.get_current_token()?;
+        let name =
+            IdentifierExpression::new(token.line, token.start, token.end, token.value.to_owned());
+        self.next_token();
+        self.next_token();
+        let mut args = Vec::new();
+        if self.get_current_token()?.token_type != TokenType::RPAREN {
+            args.push(self.try_get_value()?);
+            while self.get_current_token()?.token_type == TokenType::COMMA {
+                self.next_token();
+                args.push(self.try_get_value()?);
+            }
+        }
+        let end =
+            self.expect_token(TokenType::RPAREN, "Expected , or ) after function argument")?;
+        Ok(FunctionCall::new(name.line, name.start, end, name, args))
+    }
+}
+
+fn import_path_error(path: &ValueExpression) -> CodeError {
+    CodeError::new(
+        path.line,
+        path.start,
+        path.end,
+        "Lade expects a file name in quotes, e.g. Lade \"bibliothek.red\"",
+    )
 }
@@ -0,0 +1,425 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Cursor, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use colored::Colorize;
+
+use crate::{
+    checker::TypeChecker,
+    error::CodeError,
+    interpreter::{FileAccess, Interpreter},
+    lexer::Lexer,
+    parser::Parser,
+};
+
+pub struct TestOptions {
+    /// Seed for `Zufall` in every test
+    pub seed: Option<u64>,
+    /// Compares the output of `foo.red` with `foo.stdout`
+    pub snapshot: bool,
+    /// Writes the output of `foo.red` to `foo.stdout` instead of comparing it
+    pub bless: bool,
+    pub file_access: FileAccess,
+}
+
+/// How many test scripts passed and failed, blessed scripts count as passed.
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl Summary {
+    /// Whether there was at least one test and none of them failed.
+    pub fn success(&self) -> bool {
+        self.passed > 0 && self.failed == 0
+    }
+}
+
+/// Result of a single test script.
+enum Outcome {
+    Passed,
+    /// The snapshot was written with `--bless`
+    Blessed,
+    /// Message to print below the name of the failed script
+    Failed(String),
+}
+
+/// Runs every `.red` file in `dir` and its subdirectories as a test. A test fails when it
+/// stops with an error, e.g. a failed `Prüfe`, ends with `Beenden` and an exit code other
+/// than 0, or in snapshot mode when its output differs
+/// from its `.stdout` file. Returns how many tests passed and failed, no tests at all are not a
+/// success.
+pub fn run_tests(dir: &Path, options: &TestOptions) -> Summary {
+    let mut scripts = Vec::new();
+    if let Err(err) = find_scripts(dir, &mut scripts) {
+        println!("Error while reading directory '{}': {}", dir.display(), err);
+        return Summary::default();
+    }
+    if scripts.is_empty() {
+        println!("No test scripts found in '{}'", dir.display());
+        return Summary::default();
+    }
+    scripts.sort();
+
+    let mut failed = 0;
+    for script in &scripts {
+        match run_test(script, options) {
+            Outcome::Passed => println!("{} {}", "PASS".green().bold(), script.display()),
+            Outcome::Blessed => println!("{} {}", "BLESSED".cyan().bold(), script.display()),
+            Outcome::Failed(msg) => {
+                println!("{} {}", "FAIL".red().bold(), script.display());
+                print!("{}", msg);
+                failed += 1;
+            }
+        }
+    }
+    let summary = Summary {
+        passed: scripts.len() - failed,
+        failed,
+    };
+    println!("\n{} passed, {} failed", summary.passed, summary.failed);
+    summary
+}
+
+fn find_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_scripts(&path, scripts)?;
+        } else if path.extension().is_some_and(|extension| extension == "red") {
+            scripts.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn run_test(script: &Path, options: &TestOptions) -> Outcome {
+    let Ok(content) = fs::read_to_string(script) else {
+        return Outcome::Failed(format!("Error while reading file '{}'\n", script.display()));
+    };
+    let mut source = content.replace("\r", "");
+    source.push('\n');
+    // Input comes from `foo.stdin` if it exists, otherwise there is none
+    let input = fs::read(script.with_extension("stdin")).unwrap_or_default();
+    let output = CapturedOutput::default();
+    match run_script(script, &source, options, input, output.clone()) {
+        Ok(0) => {}
+        Ok(exit_code) => {
+            return Outcome::Failed(format!("Ended with exit code {}\n", exit_code));
+        }
+        Err(err) => return Outcome::Failed(err.render(&source)),
+    }
+    if !options.snapshot {
+        return Outcome::Passed;
+    }
+
+    let actual = String::from_utf8_lossy(&output.0.borrow()).into_owned();
+    let snapshot = script.with_extension("stdout");
+    if options.bless {
+        return match fs::write(&snapshot, &actual) {
+            Ok(()) => Outcome::Blessed,
+            Err(err) => Outcome::Failed(format!(
+                "Error while writing file '{}': {}\n",
+                snapshot.display(),
+                err
+            )),
+        };
+    }
+    match fs::read_to_string(&snapshot) {
+        Ok(expected) if expected.replace("\r", "") == actual => Outcome::Passed,
+        Ok(expected) => Outcome::Failed(format!(
+            "Output differs from '{}' ({} expected, {} actual):\n{}",
+            snapshot.display(),
+            "-".red(),
+            "+".green(),
+            diff(&expected.replace("\r", ""), &actual)
+        )),
+        Err(_) => Outcome::Failed(format!(
+            "Missing snapshot '{}', run with --bless to create it\n",
+            snapshot.display()
+        )),
+    }
+}
+
+/// Runs a script in its own interpreter and returns its exit code, the output is written
+/// to `output`.
+fn run_script(
+    script: &Path,
+    source: &str,
+    options: &TestOptions,
+    input: Vec<u8>,
+    output: CapturedOutput,
+) -> Result<i32, CodeError> {
+    let tokens = Lexer::new(source).lex()?;
+    let parsed = Parser::new(tokens).parse()?;
+    TypeChecker::new().check(&parsed)?;
+    let mut interpreter = Interpreter::with_io(
+        options.seed,
+        Box::new(Cursor::new(input)),
+        Box::new(output),
+        false,
+    )
+    .with_file_access(options.file_access.clone())
+    .with_file(script);
+    interpreter.run(parsed)
+}
+
+/// Output of a test that can still be read after the interpreter took ownership of it.
+#[derive(Clone, Default)]
+struct CapturedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Line by line difference of two texts, based on their longest common subsequence.
+pub fn diff(expected: &str, actual: &str) -> String {
+    fn lines(text: &str) -> Vec<&str> {
+        text.strip_suffix('\n')
+            .unwrap_or(text)
+            .split('\n')
+            .collect()
+    }
+    let (expected, actual) = (lines(expected), lines(actual));
+    // Only the part between the equal lines at the start and the end needs to be compared
+    let prefix = expected
+        .iter()
+        .zip(&actual)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut edits: Vec<Edit> = expected[..prefix]
+        .iter()
+        .map(|line| Edit::Same(line))
+        .collect();
+    align(
+        &expected[prefix..expected.len() - suffix],
+        &actual[prefix..actual.len() - suffix],
+        &mut edits,
+    );
+    edits.extend(
+        expected[expected.len() - suffix..]
+            .iter()
+            .map(|line| Edit::Same(line)),
+    );
+
+    // Within a changed part the removed lines are shown before the added ones
+    let mut out = String::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for edit in edits {
+        match edit {
+            Edit::Removed(line) => removed.push(line),
+            Edit::Added(line) => added.push(line),
+            Edit::Same(line) => {
+                push_changes(&mut out, &mut removed, &mut added);
+                out.push_str(&format!("  {}\n", line));
+            }
+        }
+    }
+    push_changes(&mut out, &mut removed, &mut added);
+    out
+}
+
+/// A line of the difference, kept in both texts, only in the expected or only in the actual one.
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+fn push_changes(out: &mut String, removed: &mut Vec<&str>, added: &mut Vec<&str>) {
+    for line in removed.drain(..) {
+        out.push_str(&format!("{}\n", format!("- {}", line).red()));
+    }
+    for line in added.drain(..) {
+        out.push_str(&format!("{}\n", format!("+ {}", line).green()));
+    }
+}
+
+/// Finds the edits from `expected` to `actual` with Hirschberg's algorithm, which only needs
+/// memory for two rows of lengths instead of the whole table.
+fn align<'a>(expected: &[&'a str], actual: &[&'a str], edits: &mut Vec<Edit<'a>>) {
+    match expected {
+        [] => edits.extend(actual.iter().map(|line| Edit::Added(line))),
+        _ if actual.is_empty() => edits.extend(expected.iter().map(|line| Edit::Removed(line))),
+        [line] => match actual.iter().position(|other| other == line) {
+            Some(idx) => {
+                edits.extend(actual[..idx].iter().map(|line| Edit::Added(line)));
+                edits.push(Edit::Same(line));
+                edits.extend(actual[idx + 1..].iter().map(|line| Edit::Added(line)));
+            }
+            None => {
+                edits.push(Edit::Removed(line));
+                edits.extend(actual.iter().map(|line| Edit::Added(line)));
+            }
+        },
+        _ => {
+            // Split `actual` where the common lines of both halves of `expected` add up the most
+            let mid = expected.len() / 2;
+            let upper = common_lengths(expected[..mid].iter(), actual.iter());
+            let lower = common_lengths(expected[mid..].iter().rev(), actual.iter().rev());
+            let split = (0..=actual.len())
+                .max_by_key(|&idx| upper[idx] + lower[actual.len() - idx])
+                .unwrap_or(0);
+            align(&expected[..mid], &actual[..split], edits);
+            align(&expected[mid..], &actual[split..], edits);
+        }
+    }
+}
+
+/// Lengths of the longest common subsequence of all of `expected` and the first 0, 1, ...
+/// lines of `actual`.
+fn common_lengths<'a>(
+    expected: impl Iterator<Item = &'a &'a str>,
+    actual: impl Iterator<Item = &'a &'a str> + Clone,
+) -> Vec<usize> {
+    let mut row = vec![0; actual.clone().count() + 1];
+    for line in expected {
+        let mut diagonal = 0;
+        for (idx, other) in actual.clone().enumerate() {
+            let above = row[idx + 1];
+            row[idx + 1] = if line == other {
+                diagonal + 1
+            } else {
+                above.max(row[idx])
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path, process};
+
+    use super::{diff, run_test, run_tests, Outcome, Summary, TestOptions};
+    use crate::interpreter::FileAccess;
+
+    fn options(snapshot: bool, bless: bool) -> TestOptions {
+        TestOptions {
+            seed: Some(1),
+            snapshot,
+            bless,
+            file_access: FileAccess::None,
+        }
+    }
+
+    #[test]
+    fn counts_passed_and_failed_scripts() {
+        let expected = Summary {
+            passed: 1,
+            failed: 1,
+        };
+        let dir = Path::new("tests/runner");
+        assert_eq!(run_tests(dir, &options(false, false)), expected);
+        // `gruss.red` reads `gruss.stdin` and its output matches `gruss.stdout`
+        assert_eq!(run_tests(dir, &options(true, false)), expected);
+        assert!(!expected.success());
+    }
+
+    #[test]
+    fn no_scripts_is_not_a_success() {
+        let summary = run_tests(Path::new("tests/runner/missing"), &options(false, false));
+        assert_eq!(summary, Summary::default());
+        assert!(!summary.success());
+    }
+
+    fn plain_diff(expected: &str, actual: &str) -> String {
+        colored::control::set_override(false);
+        diff(expected, actual)
+    }
+
+    #[test]
+    fn diff_of_equal_texts_has_no_changes() {
+        assert_eq!(plain_diff("a\nb\n", "a\nb\n"), "  a\n  b\n");
+    }
+
+    #[test]
+    fn diff_shows_inserted_lines() {
+        assert_eq!(plain_diff("a\nc\n", "a\nb\nc\n"), "  a\n+ b\n  c\n");
+    }
+
+    #[test]
+    fn diff_shows_deleted_lines() {
+        assert_eq!(plain_diff("a\nb\nc\n", "a\nc\n"), "  a\n- b\n  c\n");
+    }
+
+    #[test]
+    fn diff_shows_replaced_lines() {
+        assert_eq!(plain_diff("a\nb\nc\n", "a\nx\nc\n"), "  a\n- b\n+ x\n  c\n");
+    }
+
+    #[test]
+    fn diff_keeps_the_longest_common_lines() {
+        assert_eq!(
+            plain_diff("a\nb\nc\nd\ne\n", "x\nb\nd\ny\ne\nz\n"),
+            "- a\n+ x\n  b\n- c\n  d\n+ y\n  e\n+ z\n"
+        );
+    }
+
+    #[test]
+    fn diff_of_long_outputs_with_few_changes() {
+        let expected: String = (0..50_000).map(|idx| format!("{}\n", idx)).collect();
+        let actual = expected.replace("\n25000\n", "\nx\n");
+        assert_eq!(
+            plain_diff(&expected, &actual)
+                .lines()
+                .filter(|line| !line.starts_with("  "))
+                .collect::<Vec<_>>(),
+            ["- 25000", "+ x"]
+        );
+    }
+
+    #[test]
+    fn bless_then_compare() {
+        let dir = env::temp_dir().join(format!("red-snapshot-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("ausgabe.red");
+        fs::write(&script, "Output \"eins\"\nOutput \"zwei\"\n").unwrap();
+
+        assert!(matches!(
+            run_test(&script, &options(true, false)),
+            Outcome::Failed(_)
+        ));
+        assert!(matches!(
+            run_test(&script, &options(true, true)),
+            Outcome::Blessed
+        ));
+        let snapshot = script.with_extension("stdout");
+        assert_eq!(fs::read_to_string(&snapshot).unwrap(), "eins\nzwei\n");
+        assert!(matches!(
+            run_test(&script, &options(true, false)),
+            Outcome::Passed
+        ));
+
+        fs::write(&snapshot, "eins\ndrei\n").unwrap();
+        let outcome = run_test(&script, &options(true, false));
+        fs::remove_dir_all(&dir).unwrap();
+        match outcome {
+            Outcome::Failed(msg) => {
+                assert!(msg.contains("- drei"));
+                assert!(msg.contains("+ zwei"));
+            }
+            _ => panic!("a changed snapshot must fail"),
+        }
+    }
+}